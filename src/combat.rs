@@ -0,0 +1,1015 @@
+use std::collections::VecDeque;
+use std::fmt;
+use rand::{Rng, RngCore};
+use serde::Deserialize;
+use crate::mob::*;
+use crate::player::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Skill {
+    Attack,
+    Strength,
+    Defence,
+    Hitpoints,
+}
+
+// xp needed to reach a level, the usual runescape curve
+pub fn xp_for_level(level: u32) -> f64 {
+    let points: f64 = (1..level).map(|n| (n as f64 + 300.0 * 2f64.powf(n as f64 / 7.0)).floor()).sum();
+    (points / 4.0).floor()
+}
+
+// runs every hit while levelling, so it walks the curve once instead of summing it afresh for each level
+pub fn level_for_xp(xp: f64) -> u32 {
+    let mut points = 0.0;
+    for level in 1..99 {
+        points += (level as f64 + 300.0 * 2f64.powf(level as f64 / 7.0)).floor();
+        if (points / 4.0).floor() > xp {
+            return level;
+        }
+    }
+    99
+}
+
+// combat xp so far, kept alongside the levels in CombatStats which are what the fights read
+#[derive(Debug, Clone, PartialEq)]
+pub struct Experience {
+    pub attack: f64,
+    pub strength: f64,
+    pub defence: f64,
+    pub hitpoints: f64,
+}
+
+impl Experience {
+    pub fn for_stats(stats: &CombatStats) -> Self {
+        Self {
+            attack: xp_for_level(stats.att_level),
+            strength: xp_for_level(stats.str_level),
+            defence: xp_for_level(stats.def_level),
+            hitpoints: xp_for_level(stats.hp_level),
+        }
+    }
+}
+
+// potions brought along to cure poison instead of out-eating it. each one is a slot food can't use
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
+pub struct Antipoison {
+    pub per_trip: usize, // potions taken out each food trip
+    pub doses: u32, // per potion
+    pub cure_at: u32, // drink once poison is doing this much damage a minute, below that we eat through it
+    pub immunity: usize, // ticks after a dose that new poison doesn't take
+}
+
+impl Antipoison {
+    // a plain 4 dose antipoison, drunk at the first sign of poison and good for 90 seconds
+    pub fn new(per_trip: usize) -> Self {
+        Self {
+            per_trip,
+            doses: 4,
+            cure_at: 1,
+            immunity: 150,
+        }
+    }
+}
+
+impl Default for Antipoison {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+// one stack of poison, ticks until we bank
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Poison {
+    pub damage: u32,
+    pub every: usize,
+    pub since: usize, // tick it was applied
+}
+
+pub fn stacks_due(stacks: &[Poison], tick: usize) -> u32 {
+    stacks.iter()
+        .filter(|poison| tick > poison.since && (tick - poison.since).is_multiple_of(poison.every))
+        .map(|poison| poison.damage)
+        .sum()
+}
+
+// the standard prayers that matter at a grind, with osrs's boosts and drain effects
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prayer {
+    ThickSkin,
+    BurstOfStrength,
+    ClarityOfThought,
+    RockSkin,
+    SuperhumanStrength,
+    ImprovedReflexes,
+    SteelSkin,
+    UltimateStrength,
+    IncredibleReflexes,
+    ProtectFromMelee,
+}
+
+impl Prayer {
+    pub fn from_name(name: &str) -> Option<Self> {
+        let prayer = match name {
+            "thick_skin" => Prayer::ThickSkin,
+            "burst_of_strength" => Prayer::BurstOfStrength,
+            "clarity_of_thought" => Prayer::ClarityOfThought,
+            "rock_skin" => Prayer::RockSkin,
+            "superhuman_strength" => Prayer::SuperhumanStrength,
+            "improved_reflexes" => Prayer::ImprovedReflexes,
+            "steel_skin" => Prayer::SteelSkin,
+            "ultimate_strength" => Prayer::UltimateStrength,
+            "incredible_reflexes" => Prayer::IncredibleReflexes,
+            "protect_from_melee" => Prayer::ProtectFromMelee,
+            _ => return None,
+        };
+        Some(prayer)
+    }
+
+    pub fn level_needed(self) -> u32 {
+        match self {
+            Prayer::ThickSkin => 1,
+            Prayer::BurstOfStrength => 4,
+            Prayer::ClarityOfThought => 7,
+            Prayer::RockSkin => 10,
+            Prayer::SuperhumanStrength => 13,
+            Prayer::ImprovedReflexes => 16,
+            Prayer::SteelSkin => 28,
+            Prayer::UltimateStrength => 31,
+            Prayer::IncredibleReflexes => 34,
+            Prayer::ProtectFromMelee => 43,
+        }
+    }
+
+    // added to the drain counter every tick it's on
+    pub fn drain_effect(self) -> u32 {
+        match self {
+            Prayer::ThickSkin | Prayer::BurstOfStrength | Prayer::ClarityOfThought => 1,
+            Prayer::RockSkin | Prayer::SuperhumanStrength | Prayer::ImprovedReflexes => 6,
+            Prayer::SteelSkin | Prayer::UltimateStrength | Prayer::IncredibleReflexes | Prayer::ProtectFromMelee => 12,
+        }
+    }
+
+    // attack, strength and defence level multipliers
+    pub fn boosts(self) -> [f64; 3] {
+        match self {
+            Prayer::ClarityOfThought => [1.05, 1.0, 1.0],
+            Prayer::ImprovedReflexes => [1.1, 1.0, 1.0],
+            Prayer::IncredibleReflexes => [1.15, 1.0, 1.0],
+            Prayer::BurstOfStrength => [1.0, 1.05, 1.0],
+            Prayer::SuperhumanStrength => [1.0, 1.1, 1.0],
+            Prayer::UltimateStrength => [1.0, 1.15, 1.0],
+            Prayer::ThickSkin => [1.0, 1.0, 1.05],
+            Prayer::RockSkin => [1.0, 1.0, 1.1],
+            Prayer::SteelSkin => [1.0, 1.0, 1.15],
+            Prayer::ProtectFromMelee => [1.0; 3],
+        }
+    }
+
+    // share of an npc's hit of this type that never lands
+    pub fn protection(self, attack: AttackType) -> f64 {
+        match (self, attack) {
+            (Prayer::ProtectFromMelee, AttackType::Stab | AttackType::Slash | AttackType::Crush) => 1.0,
+            _ => 0.0,
+        }
+    }
+}
+
+// prayer points and what's switched on. every tick the active prayers' drain effects add up, and each
+// time that passes 60 + 2 per prayer bonus a point goes. at 0 points everything's off until we bank
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prayers {
+    pub level: u32, // also the most points we can hold
+    pub bonus: u32, // from gear, slows the drain
+    pub active: Vec<Prayer>,
+    pub points: u32,
+    pub drained: u32, // towards the next point
+}
+
+impl Prayers {
+    pub fn new(level: u32, bonus: u32, active: Vec<Prayer>) -> Self {
+        Self { level, bonus, active, points: level, drained: 0 }
+    }
+
+    pub fn is_on(&self) -> bool {
+        self.points > 0 && !self.active.is_empty()
+    }
+
+    // the best of each, prayers for the same stat don't stack
+    pub fn boosts(&self) -> [f64; 3] {
+        if !self.is_on() {
+            return [1.0; 3];
+        }
+        self.active.iter().fold([1.0; 3], |best, prayer| {
+            let boosts = prayer.boosts();
+            [best[0].max(boosts[0]), best[1].max(boosts[1]), best[2].max(boosts[2])]
+        })
+    }
+
+    pub fn protection(&self, attack: AttackType) -> f64 {
+        if !self.is_on() {
+            return 0.0;
+        }
+        self.active.iter().map(|prayer| prayer.protection(attack)).fold(0.0, f64::max)
+    }
+
+    pub fn drain(&mut self, ticks: usize) {
+        if !self.is_on() {
+            return;
+        }
+        let resistance = 60 + 2 * self.bonus;
+        self.drained += self.active.iter().map(|prayer| prayer.drain_effect()).sum::<u32>() * ticks as u32;
+        self.points = self.points.saturating_sub(self.drained / resistance);
+        self.drained %= resistance;
+    }
+
+    // the altar in every bank we use, back to full and everything back on
+    pub fn restore(&mut self) {
+        self.points = self.level;
+        self.drained = 0;
+    }
+}
+
+impl Default for Prayers {
+    fn default() -> Self {
+        Self::new(1, 0, Vec::new())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MeleeStyle {
+    Accurate,
+    Aggressive,
+    Controlled,
+    Defensive
+}
+
+impl MeleeStyle {
+    pub fn style_bonuses(&self) -> [u32; 3] {
+        match self {
+            MeleeStyle::Accurate => [3, 0, 0],
+            MeleeStyle::Aggressive => [0, 3, 0],
+            MeleeStyle::Controlled => [1, 1, 1],
+            MeleeStyle::Defensive => [0, 0, 3],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeleeDps {
+    pub str_bonus: u32,
+    pub style: MeleeStyle,
+    pub attack_type: AttackType, // stab, slash or crush, picks which of the mob's defence bonuses we roll against
+    pub accuracy: u32, // TODO make this pickable instead of assuming best DPS choice
+    pub rate: usize, // ticks per attack
+    pub def_bonus: u32, // use the def bonus of the style the mob you're fighting uses
+}
+
+// which defence bonus an attack rolls against, the combat triangle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AttackType {
+    Stab,
+    Slash,
+    Crush,
+    Ranged,
+    Magic,
+}
+
+impl AttackType {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "stab" => Some(AttackType::Stab),
+            "slash" => Some(AttackType::Slash),
+            "crush" => Some(AttackType::Crush),
+            "ranged" => Some(AttackType::Ranged),
+            "magic" => Some(AttackType::Magic),
+            _ => None,
+        }
+    }
+}
+
+// TODO magic defence should lean on the magic level too once there is one
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(from = "DefenceSpec")]
+pub struct DefenceProfile {
+    pub stab: u32,
+    pub slash: u32,
+    pub crush: u32,
+    pub ranged: u32,
+    pub magic: u32,
+}
+
+impl DefenceProfile {
+    pub fn flat(bonus: u32) -> Self {
+        Self {
+            stab: bonus,
+            slash: bonus,
+            crush: bonus,
+            ranged: bonus,
+            magic: bonus,
+        }
+    }
+
+    pub fn against(&self, attack: AttackType) -> u32 {
+        match attack {
+            AttackType::Stab => self.stab,
+            AttackType::Slash => self.slash,
+            AttackType::Crush => self.crush,
+            AttackType::Ranged => self.ranged,
+            AttackType::Magic => self.magic,
+        }
+    }
+}
+
+// how defence is written in mob files
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum DefenceSpec {
+    Flat(u32),
+    Split { stab: u32, slash: u32, crush: u32, ranged: u32, magic: u32 },
+}
+
+impl From<DefenceSpec> for DefenceProfile {
+    fn from(spec: DefenceSpec) -> Self {
+        match spec {
+            DefenceSpec::Flat(bonus) => Self::flat(bonus),
+            DefenceSpec::Split { stab, slash, crush, ranged, magic } => Self { stab, slash, crush, ranged, magic },
+        }
+    }
+}
+
+pub trait HasCombatStats {
+    fn str_level(&self) -> u32;
+
+    fn is_dead(&self) -> bool;
+
+    fn equipment_accuracy(&self) -> u32;
+
+    fn def_level(&self) -> u32;
+
+    fn att_level(&self) -> u32;
+
+    fn attack_rate(&self) -> usize;
+
+    fn equipment_strength(&self) -> u32;
+
+    fn defence(&self) -> DefenceProfile;
+
+    fn attack_type(&self) -> AttackType;
+
+    fn style_bonuses(&self) -> [u32; 3]; // invisible attack, strength and defence levels off the attack style
+
+    fn spell_max_hit(&self) -> Option<u32>; // None rolls the max hit off strength
+
+    fn prayer_boosts(&self) -> [f64; 3]; // attack, strength and defence multipliers
+
+    fn deduct_hp(&mut self, amount: u32);
+
+    fn is_npc(&self) -> bool;
+
+    fn is_player(&self) -> bool;
+
+    fn name(&self) -> &str; // for logs and traces, generic code doesn't know which side it has
+}
+
+// everything about one side's swing that doesn't need the rng
+#[derive(Debug, Clone, Copy)]
+pub struct AttackProfile {
+    pub max_hit: u32,
+    pub hit_chance: f64,
+    pub rate: usize, // ticks per attack
+}
+
+impl AttackProfile {
+    // hits roll uniformly over 1..=max_hit
+    pub fn expected_hit(&self) -> f64 {
+        self.hit_chance * (self.max_hit as f64 + 1.0) / 2.0
+    }
+
+    // a tick is 0.6 seconds
+    pub fn dps(&self) -> f64 {
+        self.expected_hit() / (self.rate as f64 * 0.6)
+    }
+}
+
+// attack, strength and defence as the rolls see them.
+// prayer multiplies the level before the style bonus and rounds down, no potion boosts assumed
+pub fn effective_levels<A: HasCombatStats>(side: &A) -> [u32; 3] {
+    let pray = |level: u32, multiplier: f64| (level as f64 * multiplier).floor() as u32;
+    let [att_prayer, str_prayer, def_prayer] = side.prayer_boosts();
+    let [att_style, str_style, def_style] = side.style_bonuses();
+    [
+        pray(side.att_level(), att_prayer) + att_style + 8,
+        pray(side.str_level(), str_prayer) + str_style + 8,
+        pray(side.def_level(), def_prayer) + def_style + 8,
+    ]
+}
+
+pub fn precompute_attack<A, B>(attacker: &A, defender: &B) -> AttackProfile
+where A: HasCombatStats, B: HasCombatStats {
+    // osrs dps calc from wiki, probably unchanged for 04
+    let [eff_att, eff_str, _] = effective_levels(attacker);
+    // ignore void bonus
+
+    // todo: level up the player, increasing max hit
+    let max_hit = match attacker.spell_max_hit() {
+        Some(max_hit) => max_hit,
+        None => {
+            let mut max_hit = eff_str;
+            max_hit *= attacker.equipment_strength() + 64;
+            max_hit += 320;
+            // no target-specific gear bonus
+            max_hit / 640 // integer division automatically rounds down
+        }
+    };
+
+    let eff_def = effective_levels(defender)[2];
+
+    let att_roll = eff_att * (attacker.equipment_accuracy() + 64);
+
+    let style_defense = defender.defence().against(attacker.attack_type());
+    let def_roll = eff_def * (style_defense + 64);
+
+    let hit_chance = if att_roll > def_roll {
+        1.0 - (def_roll as f64 + 2.0) / (2.0*(att_roll as f64 + 1.0))
+    } else {
+        att_roll as f64 / (2.0*(def_roll as f64 + 1.0))
+    };
+
+    AttackProfile {
+        max_hit,
+        hit_chance,
+        rate: attacker.attack_rate(),
+    }
+}
+
+// the only parts of a player that move mid-trial and feed into either swing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProfileKey {
+    pub att_level: u32,
+    pub str_level: u32,
+    pub def_level: u32,
+    pub on_fallback: bool,
+    pub praying: bool, // boosts only count while there are points left
+    pub loadout_generation: u64, // bumped on every equip, the bonuses live in the loadout
+}
+
+// both swings of one player/mob matchup, shared by every trial of a search since the base player is
+// fixed, only redone when a level up or weapon swap moves the key
+#[derive(Debug, Clone)]
+pub struct MatchupProfiles {
+    pub key: ProfileKey,
+    pub player: AttackProfile,
+    pub mob: AttackProfile,
+}
+
+impl MatchupProfiles {
+    pub fn new(player: &Player, mob: &RollsGemtable) -> Self {
+        Self {
+            key: player.profile_key(),
+            player: precompute_attack(player, mob),
+            mob: precompute_attack(mob, player),
+        }
+    }
+
+    pub fn refresh(&mut self, player: &Player, mob: &RollsGemtable) {
+        if player.profile_key() != self.key {
+            *self = Self::new(player, mob);
+        }
+    }
+}
+
+// average damage a tick straight off the profile the fights roll against, for sizing up a loadout
+// without running trials. per tick rather than per second like AttackProfile::dps
+pub fn expected_dps<A, B>(attacker: &A, defender: &B) -> f64
+where A: HasCombatStats, B: HasCombatStats {
+    let attack = precompute_attack(attacker, defender);
+    attack.expected_hit() / attack.rate as f64
+}
+
+pub fn player_dps_against(player: &Player, mob: &RollsGemtable) -> f64 {
+    precompute_attack(player, mob).dps()
+}
+
+// while the protection prayer lasts
+pub fn mob_dps_against(mob: &RollsGemtable, player: &Player) -> f64 {
+    precompute_attack(mob, player).dps() * (1.0 - player.prayer.protection(mob.attack_type()))
+}
+
+// one value a ScriptedRng hands out, in the form the combat code asks for it
+#[derive(Debug, Clone, Copy)]
+pub enum Draw {
+    Float(f64), // rng.random::<f64>(), in [0, 1)
+    Int(u32), // rng.random::<u32>()
+}
+
+// plays back a fixed sequence of draws so a combat branch can be forced,
+// eg Float(0.0) then Int(max_hit - 1) is a guaranteed max hit. panics once the script runs out
+#[derive(Debug, Clone, Default)]
+pub struct ScriptedRng {
+    pub script: VecDeque<Draw>,
+}
+
+impl ScriptedRng {
+    pub fn new(script: impl IntoIterator<Item = Draw>) -> Self {
+        Self {
+            script: script.into_iter().collect(),
+        }
+    }
+
+    pub fn next_draw(&mut self) -> Draw {
+        self.script.pop_front().expect("scripted rng ran out of draws")
+    }
+}
+
+impl RngCore for ScriptedRng {
+    fn next_u32(&mut self) -> u32 {
+        match self.next_draw() {
+            Draw::Int(value) => value,
+            draw => panic!("expected an int draw, script had {draw:?}"),
+        }
+    }
+
+    // rand builds an f64 out of the top 53 bits, so put the value back there
+    fn next_u64(&mut self) -> u64 {
+        match self.next_draw() {
+            Draw::Float(value) => ((value.clamp(0.0, 1.0 - f64::EPSILON) * (1u64 << 53) as f64) as u64) << 11,
+            Draw::Int(value) => value as u64,
+        }
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+}
+
+// what one side's swing did this tick, attacked is false when it wasn't their tick to swing
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct HitOutcome {
+    pub attacked: bool,
+    pub hit: bool,
+    pub damage: u32,
+}
+
+// only rolls, applying the damage is up to the caller
+pub fn run_combat_tick(tick: usize, start_tick: usize, attack: &AttackProfile, rng: &mut impl Rng) -> HitOutcome {
+    if tick % attack.rate != start_tick {
+        return HitOutcome::default();
+    }
+    if rng.random::<f64>() < attack.hit_chance {
+        let damage = rng.random::<u32>() % attack.max_hit + 1;
+        HitOutcome { attacked: true, hit: true, damage }
+    } else {
+        HitOutcome { attacked: true, hit: false, damage: 0 }
+    }
+}
+
+// how often each damage came up over `rolls` swings, index 0 is misses (a hit always does at least 1)
+pub fn hit_distribution(attack: &AttackProfile, rolls: usize, rng: &mut impl Rng) -> Vec<usize> {
+    let mut counts = vec![0; attack.max_hit as usize + 1];
+    for _ in 0..rolls {
+        let outcome = run_combat_tick(0, 0, attack, rng);
+        counts[outcome.damage as usize] += 1;
+    }
+    counts
+}
+
+// observed against the profile's own odds, misses then 1..=max_hit evenly over the hits
+pub fn print_hit_distribution(attacker: &str, defender: &str, attack: &AttackProfile, rolls: usize, rng: &mut impl Rng) {
+    let counts = hit_distribution(attack, rolls, rng);
+    println!("{attacker} vs {defender}: max hit {}, miss rate {:.2}% (expected {:.2}%)", attack.max_hit,
+        counts[0] as f64 / rolls as f64 * 100.0, (1.0 - attack.hit_chance) * 100.0);
+    for (damage, count) in counts.iter().enumerate() {
+        let expected = if damage == 0 {
+            1.0 - attack.hit_chance
+        } else {
+            attack.hit_chance / attack.max_hit as f64
+        };
+        println!("    {damage:>3}: {:>6.2}% (expected {:.2}%)", *count as f64 / rolls as f64 * 100.0, expected * 100.0);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RangedStyle {
+    Accurate,
+    Rapid,
+    Longrange
+}
+
+// TODO there's no ranged level yet, so ranging still rolls off attack/strength
+#[derive(Debug, Clone, PartialEq)]
+pub struct RangedDps {
+    pub ammo_str: u32,
+    pub accuracy: u32,
+    pub style: RangedStyle,
+    pub rate: usize, // ticks per attack on accurate, rapid is a tick faster and longrange a tick slower
+    pub def_bonus: u32,
+}
+
+impl RangedDps {
+    pub fn attack_rate(&self) -> usize {
+        match self.style {
+            RangedStyle::Rapid => (self.rate - 1).max(1),
+            RangedStyle::Accurate => self.rate,
+            // TODO longrange should let us safespot once that's modeled
+            RangedStyle::Longrange => self.rate + 1,
+        }
+    }
+
+    // accurate's +3 goes to ranged strength too
+    pub fn style_bonuses(&self) -> [u32; 3] {
+        match self.style {
+            RangedStyle::Accurate => [3, 3, 0],
+            RangedStyle::Rapid => [0; 3],
+            RangedStyle::Longrange => [0, 0, 3],
+        }
+    }
+}
+
+// a single autocast spell. TODO there's no magic level yet either, so accuracy rolls off attack
+#[derive(Debug, Clone, PartialEq)]
+pub struct MagicDps {
+    pub max_hit: u32, // fixed by the spell, strength doesn't come into it
+    pub accuracy: u32,
+    pub rate: usize, // ticks per cast, 5 for most spells
+    pub def_bonus: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Loadout {
+    Melee(MeleeDps),
+    Ranged(RangedDps),
+    Magic(MagicDps),
+}
+
+// a loadout the fights can't roll with, each one carries the loadout it was found on
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoadoutError {
+    ZeroRate(Loadout),
+    ZeroMaxHit(Loadout),
+    NotMelee(Loadout),
+}
+
+impl fmt::Display for LoadoutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadoutError::ZeroRate(loadout) => write!(f, "{loadout:?}: rate must be at least 1"),
+            LoadoutError::ZeroMaxHit(loadout) => write!(f, "{loadout:?}: max_hit must be at least 1"),
+            LoadoutError::NotMelee(loadout) => write!(f, "{loadout:?}: a melee attack has to stab, slash or crush"),
+        }
+    }
+}
+
+impl std::error::Error for LoadoutError {}
+
+impl Loadout {
+    pub fn check_rates(&self) -> Result<(), LoadoutError> {
+        let rate = match self {
+            Loadout::Melee(melee) => melee.rate,
+            Loadout::Ranged(ranged) => ranged.rate,
+            Loadout::Magic(magic) => magic.rate,
+        };
+        if rate == 0 {
+            return Err(LoadoutError::ZeroRate(self.clone()));
+        }
+        // the damage roll is taken modulo the max hit
+        if let Loadout::Magic(MagicDps { max_hit: 0, .. }) = self {
+            return Err(LoadoutError::ZeroMaxHit(self.clone()));
+        }
+        if let Loadout::Melee(melee) = self {
+            if !matches!(melee.attack_type, AttackType::Stab | AttackType::Slash | AttackType::Crush) {
+                return Err(LoadoutError::NotMelee(self.clone()));
+            }
+        }
+        Ok(())
+    }
+}
+
+// how levels get spread when all we know is a combat level
+#[derive(Debug, Clone, Copy)]
+pub enum StyleProfile {
+    Balanced, // attack, strength and defence all even
+    Pure, // 1 defence, the rest into attack and strength
+}
+
+// config files only give levels, everything starts at full hp
+#[derive(Deserialize)]
+pub struct Levels {
+    pub att_level: u32,
+    pub str_level: u32,
+    pub def_level: u32,
+    pub hp_level: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(from = "Levels")]
+pub struct CombatStats {
+    pub str_level: u32,
+    pub def_level: u32,
+    pub att_level: u32,
+    pub hp_level: u32,
+    pub current_hp: u32,
+}
+
+impl CombatStats {
+    // fresh at full hp, which is how every mob and player starts out
+    pub fn full(att_level: u32, str_level: u32, def_level: u32, hp_level: u32) -> Self {
+        Self {
+            att_level,
+            str_level,
+            def_level,
+            hp_level,
+            current_hp: hp_level,
+        }
+    }
+
+    // 2004 formula, with no prayer/ranged/magic levels tracked they're taken as 1
+    //   base  = (def + hp + prayer / 2) / 4
+    //   melee = 0.325 * (att + str)
+    //   cb    = floor(base + max(melee, ranged, magic)), the non-melee terms never win at level 1
+    pub fn combat_level(&self) -> u32 {
+        let prayer = 1;
+        let base = 0.25 * (self.def_level + self.hp_level + prayer / 2) as f64;
+        let melee = 0.325 * (self.att_level + self.str_level) as f64;
+        (base + melee).floor() as u32
+    }
+
+    // highest even spread of levels that doesn't go over the target combat level. hitpoints
+    // follow the melee levels since they're trained together, pures trail a few levels behind
+    pub fn for_combat_level(cb: u32, profile: StyleProfile) -> Self {
+        let stats_at = |level: u32| match profile {
+            StyleProfile::Balanced => Self::full(level, level, level, level.max(10)),
+            StyleProfile::Pure => Self::full(level, level, 1, level.saturating_sub(4).max(10)),
+        };
+        (1..=99)
+            .map(stats_at)
+            .take_while(|stats| stats.combat_level() <= cb)
+            .last()
+            .unwrap_or_else(|| stats_at(1))
+    }
+
+    pub fn check_hp(&self) {
+        debug_assert!(self.current_hp <= self.hp_level, "current hp {} above max {}", self.current_hp, self.hp_level);
+    }
+
+    pub fn die(&mut self) {
+        self.current_hp = 0;
+        self.check_hp();
+    }
+
+    pub fn deduct_hp(&mut self, amount: u32) {
+        if amount > self.current_hp {
+            self.die()
+        } else {
+            self.current_hp -= amount;
+        }
+        self.check_hp();
+    }
+
+    // saturating so a heal-everything amount like u32::MAX just tops us up
+    pub fn heal_hp(&mut self, amount: u32) {
+        self.current_hp = self.current_hp.saturating_add(amount).min(self.hp_level);
+        self.check_hp();
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current_hp == 0
+    }
+}
+
+impl From<Levels> for CombatStats {
+    fn from(levels: Levels) -> Self {
+        Self::full(levels.att_level, levels.str_level, levels.def_level, levels.hp_level)
+    }
+}
+
+// a degrading weapon, drains every attack and gets topped up whenever we bank
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeaponCharges {
+    pub max: u32,
+    pub left: u32,
+    pub per_attack: u32,
+    pub gp_per_charge: u64, // what recharging costs, counted against gp/hr
+    pub spare: Option<Loadout>, // whichever of the charged weapon and the fallback isn't in hand
+    pub on_fallback: bool,
+}
+
+impl WeaponCharges {
+    pub fn new(max: u32, per_attack: u32, gp_per_charge: u64, fallback: Option<Loadout>) -> Self {
+        Self {
+            max,
+            left: max,
+            per_attack,
+            gp_per_charge,
+            spare: fallback,
+            on_fallback: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::trial_rng;
+    use crate::summary::mean;
+    use crate::testing::*;
+
+    // max hits off the wiki's melee calc, ultimate strength's 15% comes off the level before the style bonus
+    #[test]
+    fn max_hits_match_the_wiki() {
+        let target = RollsGemtable::builder()
+            .name("target dummy")
+            .levels(1, 1, 1, 10)
+            .chance(0, 1)
+            .respawn_rate(1)
+            .ticks_between_trips(0)
+            .build()
+            .expect("the dummy is a valid mob");
+        let cases = [(99, 0, None, 11), (99, 82, None, 25), (60, 30, None, 10), (99, 82, Some(Prayer::UltimateStrength), 28)];
+        for (str_level, str_bonus, prayer, expected) in cases {
+            let player = melee_player(MeleeDps { str_bonus, ..bare_weapon() }, CombatStats::full(1, str_level, 1, 10))
+                .with_prayers(Prayers::new(99, 0, prayer.into_iter().collect()));
+            let max_hit = precompute_attack(&player, &target).max_hit;
+            assert_eq!(max_hit, expected, "{str_level} str +{str_bonus} with {prayer:?}");
+        }
+    }
+
+    // heals stop at max hp and hits at 0 however big they are, without overflowing on the way
+    #[test]
+    fn hp_stays_in_bounds() {
+        let mut healed = CombatStats::full(1, 1, 1, 60);
+        healed.current_hp = 59;
+        healed.heal_hp(u32::MAX);
+        let mut hit = CombatStats::full(1, 1, 1, 60);
+        hit.deduct_hp(u32::MAX);
+        assert_eq!(healed.current_hp, 60);
+        assert_eq!(hit.current_hp, 0);
+        assert!(hit.is_dead());
+    }
+
+    // protect from melee drains 12 a tick against 60 at +0 prayer, a point every 5 ticks
+    #[test]
+    fn protect_from_melee_drain() {
+        let mut prayer = Prayers::new(43, 0, vec![Prayer::ProtectFromMelee]);
+        prayer.drain(214);
+        assert!(prayer.points == 1 && prayer.is_on(), "{} points left at 214 ticks", prayer.points);
+        prayer.drain(1);
+        assert!(!prayer.is_on());
+        assert_eq!(prayer.protection(AttackType::Slash), 0.0);
+        prayer.restore();
+        assert_eq!(prayer.points, 43);
+    }
+
+    // the npc branches in precompute_attack, pinned against a dwarf hitting a fresh level 3:
+    // att roll (6 + 8) * (5 + 64) = 966 against def roll (1 + 8) * 64 = 576
+    #[test]
+    fn dwarf_against_a_level_3() {
+        let mob = builtin("dwarf");
+        let player = melee_player(bare_weapon(), CombatStats::full(1, 1, 1, 10));
+        let mut hurt = mob.clone();
+        hurt.deduct_hp(1);
+        assert!(hurt.is_npc() && !hurt.is_player() && !hurt.is_dead());
+
+        let hit_chance = precompute_attack(&mob, &player).hit_chance;
+        let expected = 1.0 - 578.0 / 1934.0;
+        assert!((hit_chance - expected).abs() < 1e-9, "got {hit_chance:.6}, expected {expected:.6}");
+    }
+
+    // the analytic damage a tick against what run_combat_tick actually rolls over a long fight, both ways
+    // round, within four standard errors
+    #[test]
+    fn expected_dps_matches_the_rolls() {
+        let mob = builtin("dwarf");
+        let player = default_player();
+        let mut rng = trial_rng(0, 0);
+        let ticks = 400_000;
+        let mut compare = |expected: f64, attack: AttackProfile| {
+            let damage: Vec<f64> = (0..ticks).map(|tick| run_combat_tick(tick, 0, &attack, &mut rng).damage as f64).collect();
+            let observed = mean(damage.iter().copied()).unwrap_or(0.0);
+            let variance = damage.iter().map(|hit| (hit - observed).powi(2)).sum::<f64>() / (ticks - 1) as f64;
+            let error = (variance / ticks as f64).sqrt();
+            assert!(expected > 0.0 && (observed - expected).abs() <= 4.0 * error,
+                "{observed:.4} a tick over {ticks} ticks, expected {expected:.4}");
+        };
+        compare(expected_dps(&player, &mob), precompute_attack(&player, &mob));
+        compare(expected_dps(&mob, &player), precompute_attack(&mob, &player));
+    }
+
+    // each melee style's invisible levels have to reach the rolls: our max hit off the strength it gets,
+    // our hit chance off the attack it gets, and the mob's hit chance off the defence it gets
+    #[test]
+    fn melee_styles_reach_the_rolls() {
+        let mob = builtin("dwarf");
+        let chance = |att_roll: u32, def_roll: u32| {
+            let (att_roll, def_roll) = (att_roll as f64, def_roll as f64);
+            if att_roll > def_roll {
+                1.0 - (def_roll + 2.0) / (2.0 * (att_roll + 1.0))
+            } else {
+                att_roll / (2.0 * (def_roll + 1.0))
+            }
+        };
+        let expected_styles = [
+            (MeleeStyle::Accurate, [3, 0, 0]),
+            (MeleeStyle::Aggressive, [0, 3, 0]),
+            (MeleeStyle::Controlled, [1, 1, 1]),
+            (MeleeStyle::Defensive, [0, 0, 3]),
+        ];
+        for (style, bonuses) in expected_styles {
+            let player = melee_player(MeleeDps { str_bonus: 56, style: style.clone(), accuracy: 40, def_bonus: 40, ..bare_weapon() }, CombatStats::full(60, 60, 60, 60));
+            let expected = bonuses.map(|bonus| 60 + bonus + 8);
+            assert_eq!(effective_levels(&player), expected, "{style:?}");
+            let ours = precompute_attack(&player, &mob);
+            let theirs = precompute_attack(&mob, &player);
+            let hit_chance = chance(expected[0] * (40 + 64), (mob.stats.def_level + 9) * (mob.defence.against(AttackType::Slash) + 64));
+            let mob_chance = chance((mob.stats.att_level + 8) * (mob.accuracy + 64), expected[2] * (player.defence().against(AttackType::Slash) + 64));
+            assert_eq!(ours.max_hit, (expected[1] * (56 + 64) + 320) / 640, "{style:?}");
+            assert!((ours.hit_chance - hit_chance).abs() < 1e-9, "{style:?} hit chance {:.4} expected {hit_chance:.4}", ours.hit_chance);
+            assert!((theirs.hit_chance - mob_chance).abs() < 1e-9, "{style:?} dwarf hit chance {:.4} expected {mob_chance:.4}", theirs.hit_chance);
+        }
+    }
+
+    // against a mob that barely guards against stab and is hard to crush, the attack type alone moves the
+    // hit chance: stab lands most, then slash, then crush. a melee loadout can't claim to be magic
+    #[test]
+    fn attack_type_picks_the_defence() {
+        let mob = RollsGemtable::builder()
+            .name("lopsided dummy")
+            .levels(1, 1, 40, 100)
+            .chance(0, 1)
+            .defence(DefenceProfile { stab: 0, slash: 60, crush: 120, ranged: 0, magic: 0 })
+            .build()
+            .expect("the dummy is a valid mob");
+        let melee = |attack_type: AttackType| MeleeDps { style: MeleeStyle::Accurate, attack_type, accuracy: 40, ..bare_weapon() };
+        let chances: Vec<(AttackType, f64)> = [AttackType::Stab, AttackType::Slash, AttackType::Crush].into_iter()
+            .map(|attack_type| {
+                let player = melee_player(melee(attack_type), CombatStats::full(40, 40, 40, 40));
+                (player.attack_type(), precompute_attack(&player, &mob).hit_chance)
+            })
+            .collect();
+        assert!(chances.windows(2).all(|pair| pair[0].1 > pair[1].1), "{chances:?}");
+        assert!(chances.iter().map(|(attack_type, _)| *attack_type).eq([AttackType::Stab, AttackType::Slash, AttackType::Crush]));
+        let magic = Loadout::Melee(melee(AttackType::Magic));
+        assert_eq!(magic.check_rates(), Err(LoadoutError::NotMelee(magic.clone())));
+    }
+
+    #[test]
+    fn spell_cant_max_0() {
+        let fizzle = Loadout::Magic(MagicDps { max_hit: 0, accuracy: 10, rate: 5, def_bonus: 0 });
+        assert_eq!(fizzle.check_rates(), Err(LoadoutError::ZeroMaxHit(fizzle.clone())));
+    }
+
+    // each branch of a swing forced through the rng: the first draw decides the hit against
+    // hit_chance, the second lands on 1..=max_hit
+    #[test]
+    fn combat_tick_branches() {
+        let attack = AttackProfile { max_hit: 10, hit_chance: 0.5, rate: 4 };
+        let swing = |draws: Vec<Draw>| run_combat_tick(5, 1, &attack, &mut ScriptedRng::new(draws));
+        // a float of 0 is under any hit chance, and an int of 0 is the lowest damage
+        assert_eq!(swing(vec![Draw::Float(0.0), Draw::Int(0)]), HitOutcome { attacked: true, hit: true, damage: 1 });
+        assert_eq!(swing(vec![Draw::Float(0.49), Draw::Int(9)]), HitOutcome { attacked: true, hit: true, damage: 10 });
+        // the modulo wraps back round instead of going over
+        assert_eq!(swing(vec![Draw::Float(0.0), Draw::Int(10)]), HitOutcome { attacked: true, hit: true, damage: 1 });
+        assert_eq!(swing(vec![Draw::Float(0.5)]), HitOutcome { attacked: true, hit: false, damage: 0 });
+        assert_eq!(swing(vec![Draw::Float(0.99)]), HitOutcome { attacked: true, hit: false, damage: 0 });
+        // nothing drawn off our tick, an empty script would panic if it were
+        let idle = run_combat_tick(6, 1, &attack, &mut ScriptedRng::default());
+        assert_eq!(idle, HitOutcome { attacked: false, hit: false, damage: 0 });
+
+        let never = AttackProfile { hit_chance: 0.0, ..attack };
+        assert_eq!(run_combat_tick(1, 1, &never, &mut ScriptedRng::new([Draw::Float(0.0)])).damage, 0);
+        let always = AttackProfile { hit_chance: 1.0, ..attack };
+        assert!(run_combat_tick(1, 1, &always, &mut ScriptedRng::new([Draw::Float(0.99), Draw::Int(3)])).hit);
+    }
+
+    // the wiki's formula at 1 prayer and 1 ranged and magic, so the melee term always wins
+    #[test]
+    fn combat_levels_match_the_wiki() {
+        let cases = [((1, 1, 1, 10), 3), ((60, 60, 40, 60), 64), ((40, 40, 40, 40), 46), ((60, 60, 1, 60), 54), ((99, 99, 99, 99), 113)];
+        for ((att, str, def, hp), cb) in cases {
+            assert_eq!(CombatStats::full(att, str, def, hp).combat_level(), cb, "{att}/{str}/{def}/{hp}");
+        }
+    }
+
+    // the highest even spread that doesn't go over, one more level on everything would
+    #[test]
+    fn levels_for_a_combat_level() {
+        let cases = [
+            (3, StyleProfile::Balanced, (1, 1, 1, 10)),
+            (64, StyleProfile::Balanced, (56, 56, 56, 56)),
+            (113, StyleProfile::Balanced, (99, 99, 99, 99)),
+            (54, StyleProfile::Pure, (61, 61, 1, 57)),
+            // under a fresh account's 3 there's nothing lower to give
+            (1, StyleProfile::Balanced, (1, 1, 1, 10)),
+        ];
+        for (cb, profile, (att, str, def, hp)) in cases {
+            let stats = CombatStats::for_combat_level(cb, profile);
+            assert_eq!((stats.att_level, stats.str_level, stats.def_level, stats.hp_level), (att, str, def, hp), "combat {cb} {profile:?}");
+            assert_eq!(stats.current_hp, hp);
+        }
+    }
+}
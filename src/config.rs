@@ -0,0 +1,98 @@
+use std::fmt;
+use std::path::Path;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use crate::mob::*;
+
+// bump whenever a config file's layout changes, old files get rejected instead of misread
+pub const CONFIG_VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    UnsupportedVersion(u32),
+    InvalidMob(String),
+    InvalidLog(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "couldn't read config: {err}"),
+            ConfigError::Parse(err) => write!(f, "couldn't parse config: {err}"),
+            ConfigError::UnsupportedVersion(version) => {
+                write!(f, "config version {version} isn't supported, expected version {CONFIG_VERSION}")
+            }
+            ConfigError::InvalidMob(reason) => write!(f, "invalid mob {reason}"),
+            ConfigError::InvalidLog(reason) => write!(f, "couldn't read drop log: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[derive(Deserialize)]
+pub struct Versioned {
+    pub version: u32,
+}
+
+// checks the version before the rest of the file so a schema change gets a clear error
+pub fn read_config<T: DeserializeOwned>(path: &Path) -> Result<T, ConfigError> {
+    let text = std::fs::read_to_string(path).map_err(ConfigError::Io)?;
+    let versioned: Versioned = toml::from_str(&text).map_err(ConfigError::Parse)?;
+    if versioned.version != CONFIG_VERSION {
+        return Err(ConfigError::UnsupportedVersion(versioned.version));
+    }
+    toml::from_str(&text).map_err(ConfigError::Parse)
+}
+
+#[derive(Deserialize)]
+pub struct MobFile {
+    pub version: u32,
+    pub mobs: Vec<RollsGemtable>,
+}
+
+pub fn load_mobs(path: &Path) -> Result<Vec<RollsGemtable>, ConfigError> {
+    let file: MobFile = read_config(path)?;
+    for mob in &file.mobs {
+        mob.check_rates().map_err(ConfigError::InvalidMob)?;
+    }
+    Ok(file.mobs)
+}
+
+// one line of a real kill log, a kill with no drop leaves the item out
+#[derive(Debug, Deserialize)]
+pub struct LoggedDrop {
+    pub mob: String,
+    pub kill: usize,
+    pub item: Option<String>,
+    #[serde(default)]
+    pub quantity: Option<usize>,
+}
+
+// json is an array of LoggedDrop, anything else is read as csv with a mob,kill,item,quantity header
+pub fn read_drop_log(path: &Path) -> Result<Vec<LoggedDrop>, ConfigError> {
+    let text = std::fs::read_to_string(path).map_err(ConfigError::Io)?;
+    if path.extension().is_some_and(|ext| ext == "json") {
+        return serde_json::from_str(&text).map_err(|err| ConfigError::InvalidLog(err.to_string()));
+    }
+    text.lines()
+        .enumerate()
+        .skip(1)
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let bad_line = || ConfigError::InvalidLog(format!("line {}: {line}", i + 1));
+            let (Some(mob), Some(kill)) = (fields.first(), fields.get(1)) else {
+                return Err(bad_line());
+            };
+            let item = fields.get(2).filter(|item| !item.is_empty()).map(|item| item.to_string());
+            let quantity = match fields.get(3).filter(|quantity| !quantity.is_empty()) {
+                Some(quantity) => Some(quantity.parse().map_err(|_| bad_line())?),
+                None => None,
+            };
+            Ok(LoggedDrop { mob: mob.to_string(), kill: kill.parse().map_err(|_| bad_line())?, item, quantity })
+        })
+        .collect()
+}
@@ -0,0 +1,486 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use serde::Deserialize;
+use crate::loot::*;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Item {
+    pub name: String,
+    pub quantity: usize,
+    pub tier: LootTier, // which part of the tables it came from, for the summary
+}
+
+// rough groupings of the drop tables, the mob's own table is Main
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LootTier {
+    #[default]
+    Main,
+    Gem,
+    RuneItem,
+    Rare,
+    MegaRare,
+}
+
+impl LootTier {
+    pub fn label(&self) -> &'static str {
+        match self {
+            LootTier::Main => "main table",
+            LootTier::Gem => "gems",
+            LootTier::RuneItem => "rune items",
+            LootTier::Rare => "rares",
+            LootTier::MegaRare => "mega-rares",
+        }
+    }
+}
+
+impl Item {
+    pub fn new(name: &str, quantity: usize) -> Self {
+        Self {
+            name: name.into(),
+            quantity,
+            tier: LootTier::Main,
+        }
+    }
+
+    pub fn with_tier(mut self, tier: LootTier) -> Self {
+        self.tier = tier;
+        self
+    }
+
+    // kg for the whole stack, noted items and anything not in the table weigh nothing
+    pub fn weight(&self) -> f64 {
+        let each = match self.name.as_str() {
+            "big_bones" => 2.0,
+            "rune_battleaxe" => 5.443,
+            "rune_kiteshield" => 5.443,
+            "rune_sq_shield" => 3.628,
+            "rune_spear" | "dragon_spear" => 2.267,
+            "dragon_med_helm" => 1.814,
+            "shield_left_half" => 4.535,
+            "runite_bar" => 1.814,
+            "dragonstone" | "uncut_diamond" | "uncut_ruby" | "uncut_emerald" | "uncut_sapphire" => 0.007,
+            "nature_talisman" | "chaos_talisman" => 0.004,
+            _ => 0.0,
+        };
+        each * self.quantity as f64
+    }
+
+    // noted items, coins, runes and ammo all share a single slot
+    pub fn is_stackable(&self) -> bool {
+        self.name.starts_with("cert_")
+            || self.name == "coins"
+            || self.name.ends_with("rune")
+            || self.name.ends_with("_arrow")
+            || self.name.ends_with("_javelin")
+    }
+
+    pub fn slots_needed(&self) -> usize {
+        if self.is_stackable() {
+            1
+        } else {
+            self.quantity
+        }
+    }
+}
+
+// a normal inventory, bigger ones model looting bags and familiars
+pub const INVENTORY_SIZE: usize = 28;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Inventory<const N: usize = INVENTORY_SIZE> {
+    pub items: [Option<Item>; N],
+    pub indices: HashMap<String, usize>,
+    pub reserved: usize, // empty slots spoken for by supplies we only count, like food
+}
+
+#[derive(Debug, Default)]
+pub struct Bank {
+    pub lookup: BTreeMap<String, usize>, // ordered so anything printed from the bank comes out the same every run
+}
+
+impl Bank {
+    // a stack caps out at usize::MAX rather than wrapping round to almost nothing. storing none of
+    // something doesn't open a stack for it
+    pub fn store(&mut self, item: &Item) {
+        if item.quantity == 0 {
+            return;
+        }
+        match self.lookup.get_mut(&item.name) {
+            Some(existing) => *existing = existing.saturating_add(item.quantity),
+            None => {
+                self.lookup.insert(item.name.clone(), item.quantity);
+            }
+        }
+    }
+
+    // all `quantity` or nothing, the stack goes once it's emptied. taking none always works
+    pub fn withdraw(&mut self, name: &str, quantity: usize) -> Option<Item> {
+        if quantity == 0 {
+            return Some(Item::new(name, 0));
+        }
+        let stored = self.lookup.get_mut(name)?;
+        *stored = stored.checked_sub(quantity)?;
+        if *stored == 0 {
+            self.lookup.remove(name);
+        }
+        Some(Item::new(name, quantity))
+    }
+}
+
+impl<const N: usize> Inventory<N> {
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    pub fn total_of(&self, item_name: &str) -> usize {
+        for item in &self.items {
+            match item {
+                Some(item) if item.name == item_name => return item.quantity,
+                _ => continue
+            }
+        }
+        0
+    }
+
+    pub fn index_of(&self, item_name: &str) -> Option<usize> {
+        self.indices.get(item_name).copied()
+    }
+
+    pub fn first_available(&self) -> Option<usize> {
+        for (i, item) in self.items.iter().enumerate() {
+            match item {
+                Some(_) => continue,
+                None => return Some(i)
+            }
+        }
+        None
+    }
+
+    // a stack of something that doesn't stack sits in one entry but takes a slot per item
+    pub fn used_slots(&self) -> usize {
+        self.items.iter().flatten().map(Item::slots_needed).sum()
+    }
+
+    pub fn free_slots(&self) -> usize {
+        N.saturating_sub(self.used_slots()).saturating_sub(self.reserved)
+    }
+
+    // stackables top up an existing stack for free, anything else needs its slots empty
+    pub fn can_loot(&self, item: &Item) -> bool {
+        if item.is_stackable() && self.index_of(&item.name).is_some() {
+            return true;
+        }
+        self.free_slots() >= item.slots_needed()
+    }
+
+    // the older name for can_loot, kept so callers from before the rename still build
+    pub fn has_space_for(&self, item: &Item) -> bool {
+        self.can_loot(item)
+    }
+
+    pub fn add_item(&mut self, item: Item) -> Result<(), InventoryError> {
+        if !self.can_loot(&item) {
+            return Err(InventoryError::Full { needed: item.slots_needed(), free: self.free_slots(), item: item.name });
+        }
+        match self.indices.get(&item.name).copied() {
+            Some(idx) => {
+                if let Some(existing) = self.items[idx].as_mut() {
+                    existing.quantity += item.quantity;
+                }
+            },
+            None => {
+                // used slots never outnumber the entries, so there's always one free when the item fits
+                let slot = self.first_available().unwrap();
+                self.indices.insert(item.name.clone(), slot);
+                self.items[slot] = Some(item);
+            }
+        }
+        self.debug_check();
+        Ok(())
+    }
+
+    // every occupied slot is indexed under its own name, every index points at a slot holding
+    // that name, nothing sits in the pack with a quantity of 0 and it all fits
+    pub fn check_invariants(&self) -> Result<(), String> {
+        if self.used_slots() > N {
+            return Err(format!("{} slots used out of {N}", self.used_slots()));
+        }
+        for (slot, item) in self.items.iter().enumerate() {
+            let Some(item) = item else {
+                continue;
+            };
+            if item.quantity == 0 {
+                return Err(format!("slot {slot} holds 0 {}", item.name));
+            }
+            match self.indices.get(&item.name) {
+                Some(idx) if *idx == slot => {},
+                Some(idx) => return Err(format!("{} is in slot {slot} but indexed at {idx}", item.name)),
+                None => return Err(format!("{} is in slot {slot} but not indexed", item.name)),
+            }
+        }
+        for (name, idx) in &self.indices {
+            match self.items.get(*idx) {
+                Some(Some(item)) if item.name == *name => {},
+                Some(Some(item)) => return Err(format!("{name} is indexed at {idx} but {} is there", item.name)),
+                _ => return Err(format!("{name} is indexed at {idx} but the slot is empty")),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn debug_check(&self) {
+        if cfg!(debug_assertions) {
+            if let Err(err) = self.check_invariants() {
+                panic!("inventory invariant broken: {err}");
+            }
+        }
+    }
+
+    pub fn value(&self, prices: &dyn PriceSource) -> u64 {
+        self.items.iter().flatten().map(|item| item_value(item, prices)).sum()
+    }
+
+    pub fn weight(&self) -> f64 {
+        self.items.iter().flatten().map(Item::weight).sum()
+    }
+
+    // slides everything to the front in the same order, leaving the free slots at the end
+    pub fn defragment(&mut self) {
+        let mut next = 0;
+        for slot in 0..N {
+            if self.items[slot].is_some() {
+                self.items.swap(next, slot);
+                next += 1;
+            }
+        }
+        self.indices = self.items.iter()
+            .enumerate()
+            .filter_map(|(slot, item)| Some((item.as_ref()?.name.clone(), slot)))
+            .collect();
+        self.debug_check();
+    }
+
+    // up to `quantity` off the stack, the slot comes free once it's emptied
+    pub fn remove(&mut self, item_name: &str, quantity: usize) -> Option<Item> {
+        let slot = self.index_of(item_name)?;
+        let held = self.items[slot].as_mut()?;
+        let taken = quantity.min(held.quantity);
+        held.quantity -= taken;
+        let item = Item::new(item_name, taken).with_tier(held.tier);
+        if held.quantity == 0 {
+            self.items[slot] = None;
+            self.indices.remove(item_name);
+        }
+        self.debug_check();
+        Some(item)
+    }
+
+    pub fn clear(&mut self) {
+        self.items.fill(None);
+        self.indices.clear();
+        self.debug_check();
+    }
+
+    pub fn bank(&mut self, bank: &mut Bank) {
+        for item in self.items.iter() {
+            match item {
+                Some(item) => {
+                    bank.store(item)
+                },
+                _ => continue
+            }
+        }
+        self.clear();
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InventoryError {
+    Full { item: String, needed: usize, free: usize },
+}
+
+impl fmt::Display for InventoryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InventoryError::Full { item, needed, free } => write!(f, "no room for {item}, needs {needed} slots with {free} free"),
+        }
+    }
+}
+
+impl std::error::Error for InventoryError {}
+
+impl<const N: usize> Default for Inventory<N> {
+    fn default() -> Self {
+        Self {
+            items: core::array::from_fn(|_| None),
+            indices: HashMap::new(),
+            reserved: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FoodKind {
+    pub name: String,
+    pub heal: u32,
+}
+
+impl FoodKind {
+    pub fn new(name: &str, heal: u32) -> Self {
+        Self {
+            name: name.into(),
+            heal,
+        }
+    }
+
+    pub fn salmon() -> Self {
+        Self::new("salmon", 9)
+    }
+
+    // cheapest first
+    pub fn tiers() -> [Self; 5] {
+        [
+            Self::new("trout", 7),
+            Self::salmon(),
+            Self::new("lobster", 12),
+            Self::new("swordfish", 14),
+            Self::new("shark", 20),
+        ]
+    }
+
+    pub fn by_name(name: &str) -> Option<Self> {
+        Self::tiers().into_iter().find(|food| food.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a pack with holes in it compacts to the front without losing or splitting anything
+    #[test]
+    fn defragment_keeps_everything() {
+        let mut inventory: Inventory = Default::default();
+        for (name, quantity) in [("coins", 500), ("big_bones", 1), ("uncut_ruby", 1), ("naturerune", 20), ("rune_spear", 1)] {
+            inventory.add_item(Item::new(name, quantity)).expect("an empty pack takes five things");
+        }
+        // eat a couple of slots out of the middle, the way using up supplies would
+        for name in ["big_bones", "naturerune"] {
+            inventory.remove(name, usize::MAX);
+        }
+        let held = |inventory: &Inventory| -> Vec<(String, usize)> {
+            inventory.items.iter().flatten().map(|item| (item.name.clone(), item.quantity)).collect()
+        };
+        let before = held(&inventory);
+        inventory.defragment();
+        assert_eq!(held(&inventory), before);
+        assert!(inventory.items.iter().skip_while(|item| item.is_some()).all(|item| item.is_none()));
+        assert_eq!(inventory.check_invariants(), Ok(()));
+    }
+
+    // five of something that doesn't stack is five slots
+    #[test]
+    fn multi_slot_item_in_a_nearly_full_pack() {
+        let mut inventory: Inventory = Default::default();
+        for i in 0..24 {
+            inventory.add_item(Item::new(&format!("junk_{i}"), 1)).expect("room for junk");
+        }
+        assert!(inventory.add_item(Item::new("big_bones", 5)).is_err());
+        assert!(inventory.can_loot(&Item::new("big_bones", 4)));
+    }
+
+    // a full pack turns anything new away instead of panicking
+    #[test]
+    fn full_inventory_rejects() {
+        let mut inventory: Inventory = Default::default();
+        for i in 0..INVENTORY_SIZE {
+            inventory.add_item(Item::new(&format!("junk_{i}"), 1)).expect("room for junk");
+        }
+        let full = inventory.add_item(Item::new("uncut_ruby", 1));
+        assert_eq!(full, Err(InventoryError::Full { item: "uncut_ruby".into(), needed: 1, free: 0 }));
+        assert_eq!(inventory.check_invariants(), Ok(()));
+    }
+
+    // five hundred notes of it is one slot however many times they're added
+    #[test]
+    fn cert_item_takes_one_slot() {
+        let mut inventory: Inventory = Default::default();
+        for _ in 0..2 {
+            inventory.add_item(Item::new("cert_big_bones", 500)).expect("notes stack");
+        }
+        assert_eq!(inventory.used_slots(), 1);
+        assert_eq!(inventory.total_of("cert_big_bones"), 1000);
+    }
+
+    // a bank stack that's already as big as it gets stays there instead of wrapping
+    #[test]
+    fn bank_store_stacks_and_caps() {
+        let mut bank = Bank::default();
+        bank.store(&Item::new("coins", 5));
+        bank.store(&Item::new("coins", 10));
+        bank.store(&Item::new("big_bones", usize::MAX));
+        bank.store(&Item::new("big_bones", 10));
+        assert_eq!(bank.lookup.get("coins"), Some(&15));
+        assert_eq!(bank.lookup.get("big_bones"), Some(&usize::MAX));
+    }
+
+    // a withdrawal is all or nothing: short stock stays put
+    #[test]
+    fn bank_partial_withdraw() {
+        let mut bank = Bank::default();
+        bank.store(&Item::new("salmon", 10));
+        assert!(bank.withdraw("salmon", 28).is_none());
+        assert_eq!(bank.withdraw("salmon", 4).map(|item| item.quantity), Some(4));
+        assert_eq!(bank.lookup.get("salmon"), Some(&6));
+    }
+
+    // taking exactly what's there empties the stack
+    #[test]
+    fn bank_exact_withdraw() {
+        let mut bank = Bank::default();
+        bank.store(&Item::new("salmon", 6));
+        assert_eq!(bank.withdraw("salmon", 6).map(|item| item.quantity), Some(6));
+        assert!(!bank.lookup.contains_key("salmon"));
+        assert!(bank.withdraw("salmon", 1).is_none());
+    }
+
+    // none of something is always there, and storing none of it doesn't leave an empty stack behind
+    #[test]
+    fn bank_withdraw_of_none() {
+        let mut bank = Bank::default();
+        bank.store(&Item::new("shark", 0));
+        assert_eq!(bank.withdraw("shark", 0).map(|item| item.quantity), Some(0));
+        assert!(bank.lookup.is_empty());
+    }
+
+    // the same deposits in any order print the same bank, sorted by name
+    #[test]
+    fn bank_prints_the_same_every_time() {
+        let deposits = [("uncut_ruby", 1), ("coins", 500), ("naturerune", 20), ("big_bones", 3)];
+        let mut forwards = Bank::default();
+        let mut backwards = Bank::default();
+        for (name, quantity) in deposits {
+            forwards.store(&Item::new(name, quantity));
+        }
+        for (name, quantity) in deposits.iter().rev() {
+            backwards.store(&Item::new(name, *quantity));
+        }
+        assert_eq!(format!("{:?}", forwards), format!("{:?}", backwards));
+        let names: Vec<&str> = forwards.lookup.keys().map(String::as_str).collect();
+        assert_eq!(names, ["big_bones", "coins", "naturerune", "uncut_ruby"]);
+    }
+
+    // has_space_for answers the same as can_loot, stack or no stack
+    #[test]
+    fn has_space_for_is_can_loot() {
+        let mut inventory: Inventory<2> = Default::default();
+        inventory.add_item(Item::new("coins", 10)).expect("an empty pack takes coins");
+        inventory.add_item(Item::new("big_bones", 1)).expect("a slot is left for bones");
+        for item in [Item::new("coins", 5), Item::new("uncut_ruby", 1)] {
+            assert_eq!(inventory.has_space_for(&item), inventory.can_loot(&item));
+        }
+        assert!(inventory.has_space_for(&Item::new("coins", 5)));
+        assert!(!inventory.has_space_for(&Item::new("uncut_ruby", 1)));
+    }
+}
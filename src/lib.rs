@@ -0,0 +1,22 @@
+// the simulation itself, main.rs is only the command line around it
+
+// per-kill diagnostics for long runs, compiles away entirely without the logging feature
+macro_rules! sim_debug {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "logging")]
+        log::debug!($($arg)*);
+    };
+}
+
+pub mod combat;
+pub mod config;
+pub mod inventory;
+pub mod loot;
+pub mod mob;
+pub mod player;
+pub mod sim;
+pub mod summary;
+pub mod validate;
+
+#[cfg(test)]
+mod testing;
@@ -0,0 +1,785 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::sync::Arc;
+use rand::Rng;
+use serde::Deserialize;
+use crate::config::*;
+use crate::inventory::*;
+use crate::mob::*;
+use crate::player::*;
+use crate::sim::*;
+
+// where gp values come from, implement this to plug in live prices
+pub trait PriceSource: fmt::Debug + Send + Sync {
+    fn price(&self, item: &str) -> Option<u32>;
+}
+
+// coins are worth exactly what they say whatever the price table thinks, unknown items are worthless
+pub fn item_value(item: &Item, prices: &dyn PriceSource) -> u64 {
+    if item.name == "coins" {
+        return item.quantity as u64;
+    }
+    prices.price(&item.name).unwrap_or(0) as u64 * item.quantity as u64
+}
+
+// a fixed table, either the built-in one or loaded from a file
+#[derive(Debug, Clone, Deserialize)]
+pub struct StaticPriceSource {
+    pub prices: HashMap<String, u32>,
+}
+
+impl StaticPriceSource {
+    pub fn from_path(path: &Path) -> Result<Self, ConfigError> {
+        read_config(path)
+    }
+}
+
+impl Default for StaticPriceSource {
+    fn default() -> Self {
+        Self {
+            prices: default_prices(),
+        }
+    }
+}
+
+impl PriceSource for StaticPriceSource {
+    fn price(&self, item: &str) -> Option<u32> {
+        self.prices.get(item).copied()
+    }
+}
+
+// rough trade values, only meant to rank drops against each other
+pub fn default_prices() -> HashMap<String, u32> {
+    [
+        ("coins", 1),
+        ("uncut_sapphire", 200),
+        ("uncut_emerald", 400),
+        ("uncut_ruby", 800),
+        ("uncut_diamond", 1500),
+        ("dragonstone", 10000),
+        ("rune_javelin", 200),
+        ("adamant_javelin", 150),
+        ("half_key1", 5000),
+        ("half_key2", 5000),
+        ("naturerune", 250),
+        ("deathrune", 250),
+        ("lawrune", 250),
+        ("rune_arrow", 200),
+        ("steel_arrow", 20),
+        ("rune_2h_sword", 38000),
+        ("rune_battleaxe", 25000),
+        ("rune_sq_shield", 23000),
+        ("rune_kiteshield", 33000),
+        ("dragon_med_helm", 60000),
+        ("runite_bar", 12000),
+        ("cert_silver_ore", 75),
+        ("rune_spear", 12000),
+        ("shield_left_half", 60000),
+        ("dragon_spear", 50000),
+        ("nature_talisman", 500),
+        ("chaos_talisman", 200),
+        ("big_bones", 300),
+        ("trout", 20),
+        ("salmon", 40),
+        ("lobster", 150),
+        ("swordfish", 300),
+        ("shark", 800),
+    ].into_iter().map(|(name, price)| (name.to_string(), price)).collect()
+}
+
+pub fn ultrarare_table(context: &GameContext, rng: &mut impl Rng) -> Option<Item> {
+    let choice = rng.random::<u32>() % 128;
+
+    match choice {
+        0..3 => {
+            Some(Item::new("naturerune", 67).with_tier(LootTier::RuneItem))
+        },
+        3..5 => {
+            Some(Item::new("adamant_javelin", 20).with_tier(LootTier::RuneItem))
+        },
+        5..7 => {
+            Some(Item::new("deathrune", 45).with_tier(LootTier::RuneItem))
+        },
+        7..9 => {
+            Some(Item::new("lawrune", 45).with_tier(LootTier::RuneItem))
+        },
+        9..11 => {
+            Some(Item::new("rune_arrow", 42).with_tier(LootTier::RuneItem))
+        },
+        11..13 => {
+            Some(Item::new("steel_arrow", 150).with_tier(LootTier::RuneItem))
+        },
+        13..16 => {
+            Some(Item::new("rune_2h_sword", 1).with_tier(LootTier::RuneItem))
+        },
+        16..19 => {
+            Some(Item::new("rune_battleaxe", 1).with_tier(LootTier::RuneItem))
+        },
+        19..21 => {
+            Some(Item::new("rune_sq_shield", 1).with_tier(LootTier::RuneItem))
+        },
+        21..22 => {
+            Some(Item::new("dragon_med_helm", 1).with_tier(LootTier::Rare))
+        },
+        22..23 => {
+            Some(Item::new("rune_kiteshield", 1).with_tier(LootTier::RuneItem))
+        },
+        23..44 => {
+            Some(Item::new("coins", 3000).with_tier(LootTier::Rare))
+        },
+        44..64 => {
+            Some(Item::new("half_key1", 1).with_tier(LootTier::Rare))
+        },
+        64..84 => {
+            Some(Item::new("half_key2", 1).with_tier(LootTier::Rare))
+        }
+        84..89 => {
+            Some(Item::new("runite_bar", 1).with_tier(LootTier::Rare))
+        },
+        89..91 => {
+            Some(Item::new("dragonstone", 1).with_tier(LootTier::Rare))
+        },
+        91..93 => {
+            Some(Item::new("cert_silver_ore", 100).with_tier(LootTier::Rare))
+        },
+        93..113 => {
+            random_jewel(context, rng)
+        },
+        113..128 => {
+            megarare_table(context, rng)
+        },
+        _ => panic!("shouldn't happen")
+    }
+}
+
+// rune spear, shield left half and dragon spear at 8, 4 and 3 in 15
+pub fn megarare_table(_context: &GameContext, rng: &mut impl Rng) -> Option<Item> {
+    let choice = rng.random::<u32>() % 15;
+
+    match choice {
+        0..8 => {
+            Some(Item::new("rune_spear", 1).with_tier(LootTier::MegaRare))
+        },
+        8..12 => {
+            Some(Item::new("shield_left_half", 1).with_tier(LootTier::MegaRare))
+        },
+        _ => {
+            Some(Item::new("dragon_spear", 1).with_tier(LootTier::MegaRare))
+        },
+    }
+}
+
+// odds a kill reaches a table off its chance in outof. the drop rate scales the chance, and rounding
+// up matches the whole-number roll in reaches_table
+pub fn access_odds(chance: u32, outof: u32, drop_rate: f64) -> f64 {
+    (chance as f64 * drop_rate).ceil().min(outof as f64) / outof as f64
+}
+
+// one access roll, see access_odds
+pub fn reaches_table(chance: u32, outof: u32, drop_rate: f64, rng: &mut impl Rng) -> bool {
+    ((rng.random::<u32>() % outof) as f64) < chance as f64 * drop_rate
+}
+
+// the ring of wealth's only say in getting onto a table is the rare drop table's access roll, the gem
+// table's stays as it is. it takes the empty share out of that roll the same as it does the gem table's
+// rows, so with it worn a kill gets there 128 in 65 as often. kept whole, chance * 128 in outof * 65
+pub fn rare_table_access(chance: u32, outof: u32, player: &Player) -> (u32, u32) {
+    (chance * 128, outof * gem_table_modulus(player))
+}
+
+// once on the gem table, the ring skips its empty rows (65 and up)
+pub fn gem_table_modulus(player: &Player) -> u32 {
+    if player.has_ring_of_wealth() {
+        65
+    } else {
+        128
+    }
+}
+
+pub fn random_jewel(context: &GameContext, rng: &mut impl Rng) -> Option<Item> {
+    let modulus = gem_table_modulus(&context.player);
+    let choice = rng.random::<u32>() % modulus;
+
+    // should never happen
+    if choice >= modulus {
+        panic!("Something is wonky with the rng/modulus")
+    }
+
+    match choice {
+        0..32 => {
+            Some(Item::new("uncut_sapphire", 1).with_tier(LootTier::Gem))
+        },
+        32..48 => {
+            Some(Item::new("uncut_emerald", 1).with_tier(LootTier::Gem))
+        },
+        48..56 => {
+            Some(Item::new("uncut_ruby", 1).with_tier(LootTier::Gem))
+        },
+        56..58 => {
+            Some(Item::new("uncut_diamond", 1).with_tier(LootTier::Gem))
+        },
+        58..59 => {
+            if context.is_members {
+                Some(Item::new("rune_javelin", 5).with_tier(LootTier::RuneItem))
+            } else {
+                None
+            }
+        }
+        59..60 => {
+            if context.is_members {
+                Some(Item::new("half_key1", 1).with_tier(LootTier::Rare))
+            } else {
+                None
+            }
+        },
+        60..61 => {
+            if context.is_members {
+                Some(Item::new("half_key2", 1).with_tier(LootTier::Rare))
+            } else {
+                None
+            }
+        },
+        61..62 => {
+            if context.is_members {
+                megarare_table(context, rng)
+            } else {
+                None
+            }
+        },
+        62..65 => {
+            if context.is_members {
+                if context.coordz() > 6400 {
+                    Some(Item::new("chaos_talisman", 1).with_tier(LootTier::Rare))
+                } else {
+                    Some(Item::new("nature_talisman", 1).with_tier(LootTier::Rare))
+                }
+            } else {
+                None
+            }
+        },
+        _ => None
+    }
+}
+
+// how many of `rolls` rolls on a table came up each item, the empty rolls counted under "nothing"
+pub fn tally_table<R: Rng>(table: impl Fn(&GameContext, &mut R) -> Option<Item>, context: &GameContext, rolls: usize, rng: &mut R) -> HashMap<String, usize> {
+    let mut seen = HashMap::new();
+    for _ in 0..rolls {
+        let name = table(context, rng).map_or_else(|| "nothing".to_string(), |item| item.name);
+        *seen.entry(name).or_insert(0) += 1;
+    }
+    seen
+}
+
+// each table's observed rates, members and f2p, to hold up against the weights out of 128
+pub fn print_table_rates<R: Rng>(player: &Player, rolls: usize, rng: &mut R) {
+    type Table<R> = fn(&GameContext, &mut R) -> Option<Item>;
+    let tables: [(&str, Table<R>); 3] = [
+        ("gem", random_jewel),
+        ("ultrarare", ultrarare_table),
+        ("megarare", megarare_table),
+    ];
+    for is_members in [true, false] {
+        let context = GameContext::new(is_members, player.clone());
+        for (table, roll) in tables {
+            let mut seen: Vec<(String, usize)> = tally_table(roll, &context, rolls, rng).into_iter().collect();
+            seen.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            println!("{table} table, {}, {rolls} rolls:", if is_members { "members" } else { "f2p" });
+            for (name, count) in seen {
+                let freq = count as f64 / rolls as f64;
+                println!("    {name:<20} {:>7.3}% ({:.2}/128)", freq * 100.0, freq * 128.0);
+            }
+        }
+    }
+}
+
+// a table entry, the quantity is rolled uniformly from the range each time it drops
+#[derive(Debug, Clone)]
+pub struct ItemSpec {
+    pub name: String,
+    pub quantity: RangeInclusive<usize>,
+    pub tier: LootTier,
+}
+
+impl ItemSpec {
+    pub fn new(name: &str, quantity: RangeInclusive<usize>) -> Self {
+        Self {
+            name: name.into(),
+            quantity,
+            tier: LootTier::Main,
+        }
+    }
+
+    pub fn with_tier(mut self, tier: LootTier) -> Self {
+        self.tier = tier;
+        self
+    }
+
+    pub fn fixed(name: &str, quantity: usize) -> Self {
+        Self::new(name, quantity..=quantity)
+    }
+
+    pub fn roll(&self, rng: &mut impl Rng) -> Item {
+        Item::new(&self.name, rng.random_range(self.quantity.clone())).with_tier(self.tier)
+    }
+}
+
+// how a table entry is written in a config file, leave out the name for an empty roll
+#[derive(Deserialize)]
+pub struct TableEntry {
+    pub weight: u32,
+    pub name: Option<String>,
+    pub quantity: Option<(usize, usize)>,
+    #[serde(default)]
+    pub tier: LootTier,
+}
+
+// data-driven drop table, weights are relative to each other
+// None entries are rolls that give nothing
+#[derive(Debug, Clone, Deserialize)]
+#[serde(from = "Vec<TableEntry>")]
+pub struct WeightedTable {
+    pub entries: Vec<(u32, Option<ItemSpec>)>,
+    pub total_weight: u32,
+}
+
+impl WeightedTable {
+    pub fn new(entries: Vec<(u32, Option<ItemSpec>)>) -> Self {
+        let total_weight = entries.iter().map(|(weight, _)| weight).sum();
+        Self { entries, total_weight }
+    }
+
+    pub fn always(item: ItemSpec) -> Self {
+        Self::new(vec![(1, Some(item))])
+    }
+
+    pub fn roll(&self, rng: &mut impl Rng) -> Option<Item> {
+        if self.total_weight == 0 {
+            return None;
+        }
+        let mut choice = rng.random::<u32>() % self.total_weight;
+        for (weight, spec) in &self.entries {
+            if choice < *weight {
+                return spec.as_ref().map(|spec| spec.roll(rng));
+            }
+            choice -= weight;
+        }
+        panic!("roll {choice} fell outside a table weighing {}", self.total_weight)
+    }
+}
+
+impl From<Vec<TableEntry>> for WeightedTable {
+    fn from(entries: Vec<TableEntry>) -> Self {
+        Self::new(entries.into_iter().map(|entry| {
+            let (min, max) = entry.quantity.unwrap_or((1, 1));
+            (entry.weight, entry.name.map(|name| ItemSpec::new(&name, min..=max).with_tier(entry.tier)))
+        }).collect())
+    }
+}
+
+// what a drop rule gets to look at when the talisman comes up
+#[derive(Debug, Clone, Copy)]
+pub struct KillContext<'a> {
+    pub mob: &'a str,
+    pub kill: usize, // counting from 1
+    pub combat_level: u32,
+    pub is_members: bool,
+    pub coordz: i32,
+}
+
+// an extra condition on the talisman on top of the tables' own members/location gating, checked
+// every time one drops. a rule that can never pass hunts forever under the talisman goal
+#[derive(Clone)]
+pub struct DropRule {
+    pub name: String, // just for printing
+    pub allows: Arc<dyn Fn(&KillContext) -> bool + Send + Sync>,
+}
+
+impl DropRule {
+    pub fn new(name: &str, allows: impl Fn(&KillContext) -> bool + Send + Sync + 'static) -> Self {
+        Self {
+            name: name.into(),
+            allows: Arc::new(allows),
+        }
+    }
+
+    pub fn min_combat(level: u32) -> Self {
+        Self::new(&format!("combat {level}+"), move |kill| kill.combat_level >= level)
+    }
+
+    // members worlds only, and south of the given z
+    pub fn members_region(max_z: i32) -> Self {
+        Self::new(&format!("members south of {max_z}"), move |kill| kill.is_members && kill.coordz <= max_z)
+    }
+
+    pub fn allows(&self, kill: &KillContext) -> bool {
+        (self.allows)(kill)
+    }
+}
+
+impl fmt::Debug for DropRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "DropRule({})", self.name)
+    }
+}
+
+// everything one kill drops, the mob's own table first and then the gem table roll
+// drop_rate scales the odds of reaching either table, 1.0 is the real rate, and the ring the rare table's
+pub fn roll_kill_drops(mob: &RollsGemtable, context: &GameContext, drop_rate: f64, rng: &mut impl Rng) -> Vec<Item> {
+    let mut drops = Vec::new();
+    if let Some(item) = mob.main_drops.as_ref().and_then(|table| table.roll(rng)) {
+        sim_debug!("{} dropped {} x{}", mob.name, item.name, item.quantity);
+        drops.push(item);
+    }
+    if reaches_table(mob.chance, mob.outof, drop_rate, rng) {
+        if let Some(item) = random_jewel(context, rng) {
+            sim_debug!("{} dropped {} x{} off the gem table", mob.name, item.name, item.quantity);
+            drops.push(item);
+        }
+    }
+    // only mobs with access roll for it, so everyone else's draws stay as they were
+    if let Some((chance, outof)) = mob.rare_table {
+        let (chance, outof) = rare_table_access(chance, outof, &context.player);
+        if reaches_table(chance, outof, drop_rate, rng) {
+            if let Some(item) = ultrarare_table(context, rng) {
+                sim_debug!("{} dropped {} x{} off the rare drop table", mob.name, item.name, item.quantity);
+                drops.push(item);
+            }
+        }
+    }
+    drops
+}
+
+// what `kills` kills would drop with no fighting, banking or looting decisions in the way
+pub fn simulate_loot(mob: &RollsGemtable, context: &GameContext, config: &SimConfig, kills: usize, rng: &mut impl Rng) -> HashMap<String, u64> {
+    let mut loot = HashMap::new();
+    for roll in 0..kills * config.drop_rolls() {
+        let kill = KillContext {
+            mob: &mob.name,
+            kill: roll / config.drop_rolls() + 1,
+            combat_level: context.player.stats.combat_level(),
+            is_members: context.is_members,
+            coordz: context.coordz(),
+        };
+        for item in roll_kill_drops(mob, context, config.drop_rate_multiplier, rng) {
+            if !config.allows_drop(&item, &kill) {
+                continue;
+            }
+            *loot.entry(item.name).or_insert(0) += item.quantity as u64;
+        }
+    }
+    loot
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LootResult {
+    Taken,
+    Left,
+    NeedsBank, // doesn't fit now, would once the loot is deposited, and is worth going for
+}
+
+// picking things up costs time, so junk below the configured value stays on the floor
+// the talisman always gets picked up, we'd drop anything to make room for it
+pub fn loot_drop(drop: &Item, inventory: &mut Inventory, config: &SimConfig, report: &mut TallyReport) -> LootResult {
+    let is_goal = drop.name == "nature_talisman";
+    if !is_goal && config.value_of(drop) < config.min_loot_value {
+        report.skip_drop();
+        return LootResult::Left;
+    }
+    // valued and reported as the real thing, only the pack sees the cert
+    let noted = config.auto_note.contains(&drop.name).then(|| Item::new(&format!("cert_{}", drop.name), drop.quantity));
+    let item = noted.as_ref().unwrap_or(drop);
+    match inventory.add_item(item.clone()) {
+        Ok(()) => {},
+        // it's what we came for, the cheapest loot goes back on the floor to make room
+        Err(InventoryError::Full { .. }) if is_goal => {
+            if !make_room(item, inventory, config, report) {
+                report.no_space();
+                return LootResult::Left;
+            }
+        },
+        Err(_) => {
+            let worth_a_trip = config.bank_for_loot.is_some_and(|min| config.value_of(drop) >= min);
+            let fits_after_bank = item.slots_needed() <= inventory.capacity().saturating_sub(inventory.reserved);
+            if worth_a_trip && fits_after_bank {
+                return LootResult::NeedsBank;
+            }
+            report.no_space();
+            return LootResult::Left;
+        }
+    }
+    report.loot(drop);
+    report.pick_up(config.loot_pickup_ticks);
+    LootResult::Taken
+}
+
+// frees a slot at a time, whichever costs least to leave behind, until `item` fits and puts it in.
+// false when it can't be made to fit: the food holds too many slots, or there's nothing left to drop
+// but more of the same
+pub fn make_room(item: &Item, inventory: &mut Inventory, config: &SimConfig, report: &mut TallyReport) -> bool {
+    if item.slots_needed() > inventory.capacity().saturating_sub(inventory.reserved) {
+        return false;
+    }
+    // the report knows noted loot by the real name, and that's what it's priced under too
+    let looted_as = |name: &str, quantity: usize| match name.strip_prefix("cert_") {
+        Some(real) if config.auto_note.contains(real) => Item::new(real, quantity),
+        _ => Item::new(name, quantity),
+    };
+    while !inventory.can_loot(item) {
+        // a stackable's slot is the whole stack, anything else frees a slot an item at a time
+        let cheapest = inventory.items.iter()
+            .flatten()
+            .filter(|held| held.name != item.name)
+            .map(|held| (held.name.clone(), if held.is_stackable() { held.quantity } else { 1 }))
+            .min_by_key(|(name, quantity)| config.value_of(&looted_as(name, *quantity)));
+        let Some((name, quantity)) = cheapest else {
+            return false;
+        };
+        if let Some(dropped) = inventory.remove(&name, quantity) {
+            sim_debug!("dropped {} x{} to make room for {}", dropped.name, dropped.quantity, item.name);
+            report.unloot(&looted_as(&dropped.name, dropped.quantity));
+        }
+    }
+    inventory.add_item(item.clone()).is_ok()
+}
+
+// odds a single kill gives the nature talisman, straight from the tables above. the drop rate
+// multiplier gets us onto either table more often, the ring onto the rare drop table, and the ring
+// makes the talisman likelier once we're on the gem table too. double drops are a second independent go at the lot
+pub fn talisman_chance(mob: &RollsGemtable, context: &GameContext, config: &SimConfig) -> f64 {
+    if !context.is_members || context.coordz() > 6400 {
+        return 0.0;
+    }
+    let modulus = gem_table_modulus(&context.player) as f64;
+    let gem_table = access_odds(mob.chance, mob.outof, config.drop_rate_multiplier);
+    // the rare drop table hands 20 of its 128 rows on to the gem table
+    let rare_table = mob.rare_table.map_or(0.0, |(chance, outof)| {
+        let (chance, outof) = rare_table_access(chance, outof, &context.player);
+        access_odds(chance, outof, config.drop_rate_multiplier) * 20.0 / 128.0
+    });
+    let per_roll = 1.0 - (1.0 - gem_table * (3.0 / modulus)) * (1.0 - rare_table * (3.0 / modulus));
+    1.0 - (1.0 - per_roll).powi(config.drop_rolls() as i32)
+}
+
+// closed form kills needed to have seen the drop at least once with the given confidence
+// ceil(ln(1 - confidence) / ln(1 - p))
+pub fn kills_for_confidence(p: f64, confidence: f64) -> f64 {
+    ((1.0 - confidence).ln() / (1.0 - p).ln()).ceil()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::combat::*;
+    use crate::testing::*;
+    use crate::validate::rate_matches;
+
+    // the player without a ring and with nothing in the pack, so the tables roll at their plain odds
+    fn plain_context() -> GameContext {
+        let mut player = default_player().with_ring_of_wealth(false);
+        player.inventory.clear();
+        GameContext::new(true, player)
+    }
+
+    #[test]
+    fn gem_table_rates() {
+        let context = plain_context();
+        let rolls = 1_000_000;
+        let seen = tally_table(random_jewel, &context, rolls, &mut trial_rng(0, 0));
+        for (name, weight) in [("uncut_sapphire", 32), ("uncut_emerald", 16), ("uncut_ruby", 8), ("uncut_diamond", 2), ("nature_talisman", 3)] {
+            let observed = seen.get(name).copied().unwrap_or(0);
+            let p = weight as f64 / 128.0;
+            assert!(rate_matches(observed, rolls, p), "{name}: {observed} in {rolls} rolls, expected rate {p:.4}");
+        }
+    }
+
+    // every roll that reaches the mega-rare table gets one of its items, whatever the draw
+    #[test]
+    fn megarare_never_empty() {
+        let context = plain_context();
+        let empty: Vec<u32> = (0..128)
+            .filter(|choice| megarare_table(&context, &mut ScriptedRng::new([Draw::Int(*choice)])).is_none())
+            .collect();
+        assert!(empty.is_empty(), "draws {empty:?} gave nothing");
+    }
+
+    // the tally behind --table-rates counts every roll, empty ones included
+    #[test]
+    fn table_tally_counts_every_roll() {
+        let context = plain_context();
+        let rolls = 100_000;
+        let seen = tally_table(random_jewel, &context, rolls, &mut trial_rng(0, 0));
+        assert_eq!(seen.values().sum::<usize>(), rolls);
+    }
+
+    // a worn ring of wealth takes the gem table's empty rows away, one carried in the pack does nothing
+    #[test]
+    fn ring_of_wealth_worn_not_carried() {
+        let mut carried = default_player().with_ring_of_wealth(false);
+        carried.inventory.clear();
+        let _ = carried.inventory.add_item(Item::new("ring_of_wealth", 1));
+        let rolls = 1_000_000;
+        let mut rng = trial_rng(0, 0);
+        for (how, player, modulus) in [("worn", default_player().with_ring_of_wealth(true), 65.0), ("carried", carried, 128.0)] {
+            let context = GameContext::new(true, player);
+            let talismans = (0..rolls)
+                .filter_map(|_| random_jewel(&context, &mut rng))
+                .filter(|item| item.name == "nature_talisman")
+                .count();
+            let p = 3.0 / modulus;
+            assert!(rate_matches(talismans, rolls, p), "{how}: {talismans} talismans in {rolls} rolls, expected rate {p:.4}");
+        }
+    }
+
+    // worn, the ring gets a kill onto the rare drop table 128 in 65 as often, 10 in 100 becomes 1280 in 6500,
+    // and leaves the gem table's 10 in 100 alone. a kill's talisman odds take both on, and the 3 in 65 once there
+    #[test]
+    fn ring_of_wealth_access() {
+        let mob = RollsGemtable::builder()
+            .name("gem dummy")
+            .levels(1, 1, 1, 1)
+            .chance(10, 100)
+            .rare_table(10, 100)
+            .build()
+            .expect("the dummy is a valid mob");
+        let config = SimConfig::default();
+        let rolls = 1_000_000;
+        let mut rng = trial_rng(0, 0);
+        for (worn, rare_access) in [(true, 1280.0 / 6500.0), (false, 0.1)] {
+            let mut player = default_player().with_ring_of_wealth(worn);
+            player.inventory.clear();
+            let context = GameContext::new(true, player);
+            assert_eq!(access_odds(mob.chance, mob.outof, 1.0), 0.1);
+            let (chance, outof) = rare_table_access(10, 100, &context.player);
+            assert_eq!(access_odds(chance, outof, 1.0), rare_access);
+            let reached = (0..rolls)
+                .filter(|_| reaches_table(chance, outof, 1.0, &mut rng))
+                .count();
+            assert!(rate_matches(reached, rolls, rare_access), "ring {worn}: reached {reached} in {rolls} kills, expected rate {rare_access}");
+            let talismans = (0..rolls)
+                .flat_map(|_| roll_kill_drops(&mob, &context, 1.0, &mut rng))
+                .filter(|item| item.name == "nature_talisman")
+                .count();
+            let p = talisman_chance(&mob, &context, &config);
+            assert!(rate_matches(talismans, rolls, p), "ring {worn}: {talismans} talismans at {p:.5} a kill");
+        }
+    }
+
+    // every edge of every match arm, fed straight in through a scripted rng so a range that shifts by
+    // one fails here rather than only nudging the statistics. draws after the first go to a nested table
+    #[test]
+    fn table_boundaries() {
+        type Table = fn(&GameContext, &mut ScriptedRng) -> Option<Item>;
+        type Case<'a> = (&'a [u32], Option<&'a str>);
+        let context = plain_context();
+        let tables: [(&str, Table, &[Case]); 3] = [
+            ("ultrarare", ultrarare_table, &[
+                (&[0], Some("naturerune")), (&[2], Some("naturerune")), (&[3], Some("adamant_javelin")),
+                (&[4], Some("adamant_javelin")), (&[5], Some("deathrune")), (&[6], Some("deathrune")),
+                (&[7], Some("lawrune")), (&[8], Some("lawrune")), (&[9], Some("rune_arrow")), (&[10], Some("rune_arrow")),
+                (&[11], Some("steel_arrow")), (&[12], Some("steel_arrow")), (&[13], Some("rune_2h_sword")),
+                (&[15], Some("rune_2h_sword")), (&[16], Some("rune_battleaxe")), (&[18], Some("rune_battleaxe")),
+                (&[19], Some("rune_sq_shield")), (&[20], Some("rune_sq_shield")), (&[21], Some("dragon_med_helm")),
+                (&[22], Some("rune_kiteshield")), (&[23], Some("coins")), (&[43], Some("coins")),
+                (&[44], Some("half_key1")), (&[63], Some("half_key1")), (&[64], Some("half_key2")), (&[83], Some("half_key2")),
+                (&[84], Some("runite_bar")), (&[88], Some("runite_bar")), (&[89], Some("dragonstone")), (&[90], Some("dragonstone")),
+                (&[91], Some("cert_silver_ore")), (&[92], Some("cert_silver_ore")),
+                (&[93, 0], Some("uncut_sapphire")), (&[112, 64], Some("nature_talisman")),
+                (&[113, 0], Some("rune_spear")), (&[127, 14], Some("dragon_spear")), (&[128], Some("naturerune")),
+            ]),
+            ("megarare", megarare_table, &[
+                (&[0], Some("rune_spear")), (&[7], Some("rune_spear")), (&[8], Some("shield_left_half")),
+                (&[11], Some("shield_left_half")), (&[12], Some("dragon_spear")), (&[14], Some("dragon_spear")),
+                (&[15], Some("rune_spear")), (&[127], Some("rune_spear")),
+            ]),
+            ("gem", random_jewel, &[
+                (&[0], Some("uncut_sapphire")), (&[31], Some("uncut_sapphire")), (&[32], Some("uncut_emerald")),
+                (&[47], Some("uncut_emerald")), (&[48], Some("uncut_ruby")), (&[55], Some("uncut_ruby")),
+                (&[56], Some("uncut_diamond")), (&[57], Some("uncut_diamond")), (&[58], Some("rune_javelin")),
+                (&[59], Some("half_key1")), (&[60], Some("half_key2")), (&[61, 12], Some("dragon_spear")),
+                (&[62], Some("nature_talisman")), (&[64], Some("nature_talisman")), (&[65], None), (&[127], None),
+            ]),
+        ];
+        for (name, table, cases) in tables {
+            for (draws, expected) in cases {
+                let mut rng = ScriptedRng::new(draws.iter().map(|draw| Draw::Int(*draw)));
+                let got = table(&context, &mut rng).map(|item| item.name);
+                assert_eq!(got.as_deref(), *expected, "{name} table on {draws:?}");
+                assert!(rng.script.is_empty(), "{name} table left draws of {draws:?} unused");
+            }
+        }
+    }
+
+    // a doubled drop rate should see the talisman twice as often, so half the kills to get it
+    #[test]
+    fn doubled_drop_rate() {
+        let mob = builtin("dwarf");
+        let context = plain_context();
+        let p = talisman_chance(&mob, &context, &SimConfig::default());
+        let rolls = 1_000_000;
+        let mut rng = trial_rng(0, 0);
+        let observed = (0..rolls)
+            .flat_map(|_| roll_kill_drops(&mob, &context, 2.0, &mut rng))
+            .filter(|item| item.name == "nature_talisman")
+            .count();
+        assert!(rate_matches(observed, rolls, 2.0 * p), "{observed} talismans in {rolls} kills, expected rate {:.5}", 2.0 * p);
+    }
+
+    // the talisman doesn't get left for want of space: one of the cheapest things in the pack goes back
+    // on the floor instead
+    #[test]
+    fn talisman_makes_room() {
+        let config = SimConfig::default();
+        let mut inventory: Inventory = Default::default();
+        let mut report = TallyReport::new();
+        for item in [Item::new("uncut_ruby", 20), Item::new("big_bones", 8)] {
+            report.loot(&item);
+            let _ = inventory.add_item(item);
+        }
+        let result = loot_drop(&Item::new("nature_talisman", 1), &mut inventory, &config, &mut report);
+        assert_eq!(result, LootResult::Taken);
+        assert_eq!((inventory.total_of("uncut_ruby"), inventory.total_of("big_bones"), inventory.total_of("nature_talisman")), (20, 7, 1));
+        assert_eq!((report.loot.get("big_bones"), report.loot.get("nature_talisman")), (Some(&7), Some(&1)));
+    }
+
+    // when the food holds every slot there's nothing to drop, so the talisman stays where it fell
+    #[test]
+    fn talisman_left_by_a_pack_of_food() {
+        let mut inventory: Inventory = Default::default();
+        inventory.reserved = inventory.capacity();
+        let mut report = TallyReport::new();
+        let result = loot_drop(&Item::new("nature_talisman", 1), &mut inventory, &SimConfig::default(), &mut report);
+        assert_eq!(result, LootResult::Left);
+        assert!(!report.loot.contains_key("nature_talisman"));
+        assert_eq!(report.drops_no_space, 1);
+    }
+
+    // the quantity draw spread over 0..=u32::MAX lands on the range's ends at the ends and its middle
+    // halfway, 10..=30 being 21 values with 20 in the middle. a fixed quantity comes out the same whatever the draw
+    #[test]
+    fn quantity_range_ends_and_middle() {
+        let spec = ItemSpec::new("coins", 10..=30);
+        let quantity = |draw: u32| spec.roll(&mut ScriptedRng::new([Draw::Int(draw)])).quantity;
+        assert_eq!(quantity(0), 10);
+        assert_eq!(quantity(u32::MAX), 30);
+        assert_eq!(quantity(1 << 31), 20);
+        assert_eq!(ItemSpec::fixed("coins", 7).roll(&mut ScriptedRng::new([Draw::Int(u32::MAX)])).quantity, 7);
+    }
+
+    // a pack that's all food bar the coins already in it still takes more coins onto the stack, worth
+    // their face value against min_loot_value, where a drop that needs a slot of its own is left
+    #[test]
+    fn coins_stack_in_a_full_pack() {
+        let config = SimConfig { min_loot_value: 50, ..SimConfig::default() };
+        let mut inventory: Inventory = Default::default();
+        let _ = inventory.add_item(Item::new("coins", 10));
+        inventory.reserved = inventory.capacity() - 1;
+        let mut report = TallyReport::new();
+        assert_eq!(loot_drop(&Item::new("coins", 100), &mut inventory, &config, &mut report), LootResult::Taken);
+        assert_eq!(inventory.total_of("coins"), 110);
+        assert_eq!(report.loot.get("coins"), Some(&100));
+        assert_eq!(loot_drop(&Item::new("uncut_ruby", 1), &mut inventory, &config, &mut report), LootResult::Left);
+    }
+}
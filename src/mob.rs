@@ -0,0 +1,497 @@
+use serde::Deserialize;
+use crate::combat::*;
+use crate::loot::*;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RollsGemtable {
+    pub name: String,
+    pub chance: u32,
+    pub outof: u32,
+    pub stats: CombatStats,
+    pub ticks_between_trips: usize,
+    pub available_npcs: u32,
+    pub attack_rate: usize,
+    pub strength: u32,
+    pub accuracy: u32, // with chosen combat style
+    #[serde(alias = "style_defense")]
+    pub defence: DefenceProfile, // a single number in mob files means the same bonus against every style
+    pub respawn_rate: usize, // ticks between respawns
+    #[serde(default)]
+    pub aggressive: bool, // attacks on sight instead of waiting to be hit
+    #[serde(default)]
+    pub main_drops: Option<WeightedTable>, // the mob's own table, the gem table is rolled on top
+    #[serde(default)]
+    pub on_player_hit: Option<Effect>, // what hitting it does back to us
+    #[serde(default)]
+    pub rare_table: Option<(u32, u32)>, // chance in outof of a kill reaching the rare drop table, on top of the gem table
+}
+
+// something a mob does to the player whenever the player lands a hit
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Effect {
+    Recoil { damage: u32 },
+    Poison { damage: u32, every: usize }, // each hit adds another stack
+}
+
+impl RollsGemtable {
+    // the tick loop takes these modulo or waits on them, so 0 would panic or never respawn
+    pub fn check_rates(&self) -> Result<(), String> {
+        let rates = [("attack_rate", self.attack_rate), ("respawn_rate", self.respawn_rate), ("outof", self.outof as usize)];
+        match rates.iter().find(|(_, rate)| *rate == 0) {
+            Some((field, _)) => Err(format!("{}: {field} must be at least 1", self.name)),
+            None if self.rare_table.is_some_and(|(_, outof)| outof == 0) => Err(format!("{}: rare_table outof must be at least 1", self.name)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn builder() -> RollsGemtableBuilder {
+        RollsGemtableBuilder::default()
+    }
+}
+
+// mobs written out in code, the mob files go through serde instead. only the name, levels and
+// chance have to be given, everything else starts at a plain one-spawn melee mob
+#[derive(Debug, Clone)]
+pub struct RollsGemtableBuilder {
+    mob: RollsGemtable,
+}
+
+impl Default for RollsGemtableBuilder {
+    fn default() -> Self {
+        Self {
+            mob: RollsGemtable {
+                name: String::new(),
+                chance: 0,
+                outof: 0,
+                stats: CombatStats::full(1, 1, 1, 1),
+                ticks_between_trips: 100,
+                available_npcs: 1,
+                attack_rate: 4,
+                strength: 0,
+                accuracy: 0,
+                defence: DefenceProfile::flat(0),
+                respawn_rate: 100, // the game's default when a spawn doesn't give one
+                aggressive: false,
+                main_drops: None,
+                on_player_hit: None,
+                rare_table: None,
+            },
+        }
+    }
+}
+
+impl RollsGemtableBuilder {
+    pub fn name(mut self, name: &str) -> Self {
+        self.mob.name = name.to_string();
+        self
+    }
+
+    // at full hp
+    pub fn levels(self, att_level: u32, str_level: u32, def_level: u32, hp_level: u32) -> Self {
+        self.stats(CombatStats::full(att_level, str_level, def_level, hp_level))
+    }
+
+    pub fn stats(mut self, stats: CombatStats) -> Self {
+        self.mob.stats = stats;
+        self
+    }
+
+    // the gem table is rolled `chance` times in `outof`
+    pub fn chance(mut self, chance: u32, outof: u32) -> Self {
+        self.mob.chance = chance;
+        self.mob.outof = outof;
+        self
+    }
+
+    pub fn attack_rate(mut self, ticks: usize) -> Self {
+        self.mob.attack_rate = ticks;
+        self
+    }
+
+    pub fn accuracy(mut self, accuracy: u32) -> Self {
+        self.mob.accuracy = accuracy;
+        self
+    }
+
+    pub fn strength(mut self, strength: u32) -> Self {
+        self.mob.strength = strength;
+        self
+    }
+
+    pub fn defence(mut self, defence: DefenceProfile) -> Self {
+        self.mob.defence = defence;
+        self
+    }
+
+    pub fn respawn_rate(mut self, ticks: usize) -> Self {
+        self.mob.respawn_rate = ticks;
+        self
+    }
+
+    pub fn available_npcs(mut self, npcs: u32) -> Self {
+        self.mob.available_npcs = npcs;
+        self
+    }
+
+    pub fn ticks_between_trips(mut self, ticks: usize) -> Self {
+        self.mob.ticks_between_trips = ticks;
+        self
+    }
+
+    pub fn aggressive(mut self, aggressive: bool) -> Self {
+        self.mob.aggressive = aggressive;
+        self
+    }
+
+    pub fn main_drops(mut self, table: WeightedTable) -> Self {
+        self.mob.main_drops = Some(table);
+        self
+    }
+
+    pub fn rare_table(mut self, chance: u32, outof: u32) -> Self {
+        self.mob.rare_table = Some((chance, outof));
+        self
+    }
+
+    pub fn on_player_hit(mut self, effect: Effect) -> Self {
+        self.mob.on_player_hit = Some(effect);
+        self
+    }
+
+    // the same rate checks a mob file gets, and it can't start the fight hurt past its own max
+    pub fn build(self) -> Result<RollsGemtable, String> {
+        self.mob.check_rates()?;
+        let stats = &self.mob.stats;
+        if stats.current_hp > stats.hp_level {
+            return Err(format!("{}: current_hp {} is over hp_level {}", self.mob.name, stats.current_hp, stats.hp_level));
+        }
+        Ok(self.mob)
+    }
+}
+
+impl HasCombatStats for RollsGemtable {
+    fn is_npc(&self) -> bool {
+        true
+    }
+
+    fn str_level(&self) -> u32 {
+        self.stats.str_level
+    }
+
+    fn att_level(&self) -> u32 {
+        self.stats.att_level
+    }
+
+    fn def_level(&self) -> u32 {
+        self.stats.def_level
+    }
+
+    fn deduct_hp(&mut self, amount: u32) {
+        self.stats.deduct_hp(amount)
+    }
+
+    fn attack_rate(&self) -> usize {
+        self.attack_rate
+    }
+
+    fn equipment_accuracy(&self) -> u32 {
+        self.accuracy
+    }
+
+    fn equipment_strength(&self) -> u32 {
+        self.strength
+    }
+
+    fn is_dead(&self) -> bool {
+        self.stats.is_dead()
+    }
+
+    fn is_player(&self) -> bool {
+        false
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn defence(&self) -> DefenceProfile {
+        self.defence
+    }
+
+    // every mob is melee for now and the player's defence is flat anyway
+    fn attack_type(&self) -> AttackType {
+        AttackType::Slash
+    }
+
+    // npcs have no style, these keep them on the +1 strength and def + 9 they've always had
+    fn style_bonuses(&self) -> [u32; 3] {
+        [0, 1, 1]
+    }
+
+    fn spell_max_hit(&self) -> Option<u32> {
+        None
+    }
+
+    fn prayer_boosts(&self) -> [f64; 3] {
+        [1.0; 3]
+    }
+}
+
+impl HasCombatStats for &mut RollsGemtable {
+    fn is_npc(&self) -> bool {
+        true
+    }
+
+    fn str_level(&self) -> u32 {
+        self.stats.str_level
+    }
+
+    fn att_level(&self) -> u32 {
+        self.stats.att_level
+    }
+
+    fn def_level(&self) -> u32 {
+        self.stats.def_level
+    }
+
+    fn deduct_hp(&mut self, amount: u32) {
+        self.stats.deduct_hp(amount)
+    }
+
+    fn attack_rate(&self) -> usize {
+       self.attack_rate
+    }
+
+    fn equipment_accuracy(&self) -> u32 {
+        self.accuracy
+    }
+
+    fn equipment_strength(&self) -> u32 {
+        self.strength
+    }
+
+    fn is_dead(&self) -> bool {
+        self.stats.is_dead()
+    }
+
+    fn is_player(&self) -> bool {
+        false
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn defence(&self) -> DefenceProfile {
+        self.defence
+    }
+
+    // every mob is melee for now and the player's defence is flat anyway
+    fn attack_type(&self) -> AttackType {
+        AttackType::Slash
+    }
+
+    // npcs have no style, these keep them on the +1 strength and def + 9 they've always had
+    fn style_bonuses(&self) -> [u32; 3] {
+        [0, 1, 1]
+    }
+
+    fn spell_max_hit(&self) -> Option<u32> {
+        None
+    }
+
+    fn prayer_boosts(&self) -> [f64; 3] {
+        [1.0; 3]
+    }
+}
+
+pub fn builtin_candidates() -> Vec<RollsGemtable> {
+    [
+        RollsGemtable::builder()
+            .name("dwarf")
+            .levels(6, 6, 6, 10)
+            .chance(1, 129)
+            .accuracy(5)
+            .strength(7)
+            .respawn_rate(50)
+            .available_npcs(5),
+        RollsGemtable::builder()
+            .name("jogre")
+            .levels(43, 43, 43, 60)
+            .chance(1, 129)
+            .attack_rate(6)
+            .accuracy(22)
+            .strength(20)
+            .respawn_rate(30)
+            .available_npcs(8)
+            .ticks_between_trips(200),
+        RollsGemtable::builder()
+            .name("ice giant")
+            .levels(40, 40, 40, 70)
+            .chance(4, 129)
+            .attack_rate(5)
+            .accuracy(29)
+            .strength(31)
+            .defence(DefenceProfile::flat(3))
+            .respawn_rate(30)
+            .available_npcs(9) // frozen waste plateau
+            .ticks_between_trips(200)
+            .main_drops(WeightedTable::always(ItemSpec::fixed("big_bones", 1))),
+        RollsGemtable::builder()
+            .name("paladin")
+            .levels(54, 54, 54, 57)
+            .chance(2, 129)
+            .attack_rate(5)
+            .accuracy(20)
+            .strength(22)
+            .defence(DefenceProfile::flat(84))
+            .respawn_rate(50)
+            .available_npcs(13),
+        RollsGemtable::builder()
+            .name("pirate")
+            .levels(21, 21, 21, 20)
+            .chance(1, 129)
+            .attack_rate(5)
+            .accuracy(8)
+            .strength(10)
+            .defence(DefenceProfile::flat(2))
+            .respawn_rate(25)
+            .available_npcs(8) // brimhaven pub
+            .ticks_between_trips(50),
+        RollsGemtable::builder()
+            .name("armed skeleton")
+            .levels(24, 24, 24, 17)
+            .chance(2, 129)
+            .accuracy(15)
+            .strength(14)
+            .defence(DefenceProfile::flat(11))
+            .respawn_rate(60)
+            .available_npcs(5) // se crandor, north of edgeville
+            .ticks_between_trips(100), // edgeville
+        RollsGemtable::builder()
+            .name("chaos dwarf")
+            .levels(38, 42, 28, 61)
+            .chance(5, 129)
+            .accuracy(13)
+            .strength(9)
+            .defence(DefenceProfile::flat(34))
+            .respawn_rate(150)
+            .available_npcs(3) // or 4, with a much farther bank distance
+            .ticks_between_trips(400),
+        RollsGemtable::builder()
+            .name("lv28 hobgoblin")
+            .levels(22, 24, 24, 29)
+            .chance(2, 129)
+            .respawn_rate(100) // default rate is 100 when unspecified
+            .available_npcs(10) // crafting guild, 8 for outpost (investigate)
+            .ticks_between_trips(150),
+        RollsGemtable::builder()
+            .name("lv42 hobgoblin")
+            .levels(33, 31, 36, 49)
+            .chance(2, 129)
+            .accuracy(8)
+            .strength(10)
+            .defence(DefenceProfile::flat(1))
+            .respawn_rate(100) // TODO get a source for the real respawn rate
+            .available_npcs(8) // 10 crafting guild, 8 for outpost (investigate)
+            .ticks_between_trips(250),
+        RollsGemtable::builder()
+            .name("fire giant") // questionable if they can drop nature tally, will be camped
+            .levels(65, 65, 65, 111)
+            .chance(11, 129)
+            .attack_rate(5)
+            .accuracy(29)
+            .strength(31)
+            .defence(DefenceProfile::flat(3))
+            .respawn_rate(30)
+            .available_npcs(1) // or 4, in the other room. heavily competitive, maybe only get 1 or 2
+            .ticks_between_trips(300)
+            .main_drops(WeightedTable::always(ItemSpec::fixed("big_bones", 1))),
+        RollsGemtable::builder()
+            .name("black knight")
+            .levels(25, 25, 25, 42)
+            .chance(3, 129)
+            .attack_rate(5)
+            .accuracy(18)
+            .strength(16)
+            .defence(DefenceProfile::flat(76))
+            .respawn_rate(25)
+            .available_npcs(5)
+            .ticks_between_trips(250),
+        RollsGemtable::builder()
+            .name("barbarian")
+            .levels(6, 5, 5, 14)
+            .chance(1, 129)
+            .attack_rate(6)
+            .accuracy(8)
+            .strength(10)
+            .defence(DefenceProfile::flat(1))
+            .respawn_rate(25)
+            .available_npcs(5) // longhall or running around
+            .ticks_between_trips(75), // running over to fishing spot
+        RollsGemtable::builder()
+            .name("hill giant")
+            .levels(18, 22, 26, 35)
+            .chance(3, 129)
+            .attack_rate(6)
+            .accuracy(18)
+            .strength(16)
+            .respawn_rate(30)
+            .available_npcs(6) // north of observatory
+            .ticks_between_trips(200) // can fish trout/salmon at observatory pond
+            .main_drops(WeightedTable::always(ItemSpec::fixed("big_bones", 1))),
+        RollsGemtable::builder()
+            .name("moss giant")
+            .levels(30, 30, 30, 60)
+            .chance(4, 129)
+            .attack_rate(6)
+            .accuracy(33)
+            .strength(31)
+            .respawn_rate(30)
+            .available_npcs(5) // brimhaven island
+            .ticks_between_trips(200)
+            .main_drops(WeightedTable::always(ItemSpec::fixed("big_bones", 1))),
+    ]
+    .into_iter()
+    .map(|builder| builder.build().expect("builtin mobs are valid"))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the builder has to give exactly the struct it stands in for
+    #[test]
+    fn builder_matches_the_literal() {
+        let literal = RollsGemtable {
+            name: "dwarf".into(),
+            chance: 1,
+            outof: 129,
+            stats: CombatStats::full(6, 6, 6, 10),
+            ticks_between_trips: 100,
+            available_npcs: 1,
+            attack_rate: 4,
+            strength: 7,
+            accuracy: 5,
+            defence: DefenceProfile::flat(0),
+            respawn_rate: 100,
+            aggressive: false,
+            main_drops: None,
+            on_player_hit: None,
+            rare_table: None,
+        };
+        let built = RollsGemtable::builder().name("dwarf").levels(6, 6, 6, 10).chance(1, 129).strength(7).accuracy(5).build();
+        assert_eq!(format!("{built:?}"), format!("{:?}", Ok::<_, String>(literal)));
+    }
+
+    // and turn away mobs that can't fight
+    #[test]
+    fn builder_rejects_bad_mobs() {
+        assert!(RollsGemtable::builder().name("dwarf").levels(6, 6, 6, 10).build().is_err());
+        let mut overhealed = CombatStats::full(6, 6, 6, 10);
+        overhealed.current_hp = 11;
+        assert!(RollsGemtable::builder().name("dwarf").stats(overhealed).chance(1, 129).build().is_err());
+    }
+}
@@ -0,0 +1,584 @@
+use crate::combat::*;
+use crate::inventory::*;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerCoords {
+    pub x: i32, // east/west
+    pub y: i32, // vertical aka dungeons
+    pub z: i32, // north/south
+}
+
+impl PlayerCoords {
+    pub fn new(x: i32, y: i32, z: i32) -> Self {
+        Self { x, y, z }
+    }
+
+    pub fn coordz(&self) -> i32 {
+        self.z
+    }
+}
+
+pub struct GameContext {
+    pub is_members: bool,
+    pub player: Player,
+}
+
+impl GameContext {
+    pub fn coordz(&self) -> i32 {
+        self.player.coords.coordz()
+    }
+
+    pub fn new(is_members: bool, player: Player) -> Self {
+        Self {
+            is_members,
+            player,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Player {
+    pub loadout: Loadout,
+    pub inventory: Inventory,
+    pub coords: PlayerCoords,
+    pub stats: CombatStats,
+    pub charges: Option<WeaponCharges>, // None for gear that never degrades
+    pub poisons: Vec<Poison>,
+    pub cured_poisons: Vec<Poison>, // stacks antipoison dealt with, still counted to see what it saved
+    pub poison_immune_until: usize,
+    pub xp: Experience,
+    pub base_stats: CombatStats, // levels as configured, what a new trial starts from
+    pub base_loadout: Loadout, // gear as configured, put back in hand for a new trial
+    pub loadout_generation: u64, // counts equips, so a cached profile can tell the gear changed
+    pub energy: u32, // run energy in hundredths of a percent
+    pub prayer: Prayers,
+    pub ring_of_wealth: bool, // worn, so it never takes a slot or goes in the bank
+}
+
+pub const MAX_ENERGY: u32 = 10_000;
+
+impl Player {
+    pub fn new(loadout: Loadout, inventory: Inventory, coords: PlayerCoords, stats: CombatStats) -> Result<Self, LoadoutError> {
+        loadout.check_rates()?;
+        let xp = Experience::for_stats(&stats);
+        let base_stats = stats.clone();
+        let base_loadout = loadout.clone();
+        Ok(Self {
+            loadout, inventory, coords, stats, charges: None, poisons: Vec::new(), xp, base_stats,
+            base_loadout, loadout_generation: 0,
+            cured_poisons: Vec::new(), poison_immune_until: 0,
+            energy: MAX_ENERGY,
+            prayer: Prayers::default(),
+            ring_of_wealth: false,
+        })
+    }
+
+    // same gear, different levels, which is also what a new trial resets to
+    pub fn with_stats(mut self, stats: CombatStats) -> Self {
+        self.base_stats = stats.clone();
+        self.stats = stats;
+        self.xp = Experience::for_stats(&self.stats);
+        self
+    }
+
+    pub fn with_charges(mut self, charges: WeaponCharges) -> Result<Self, LoadoutError> {
+        if let Some(fallback) = &charges.spare {
+            fallback.check_rates()?;
+        }
+        self.charges = Some(charges);
+        Ok(self)
+    }
+
+    pub fn with_prayers(mut self, prayer: Prayers) -> Self {
+        self.prayer = prayer;
+        self
+    }
+
+    pub fn with_ring_of_wealth(mut self, worn: bool) -> Self {
+        self.ring_of_wealth = worn;
+        self
+    }
+
+    pub fn has_ring_of_wealth(&self) -> bool {
+        self.ring_of_wealth
+    }
+
+    // drains one attack's worth, switching to the fallback when the charged weapon runs dry.
+    // false when it's dry with nothing to fall back on and we have to go recharge
+    pub fn use_charges(&mut self) -> bool {
+        let Some(charges) = self.charges.as_mut() else {
+            return true;
+        };
+        if charges.on_fallback {
+            return true;
+        }
+        charges.left = charges.left.saturating_sub(charges.per_attack);
+        if charges.left > 0 {
+            return true;
+        }
+        let Some(fallback) = charges.spare.take() else {
+            return false;
+        };
+        charges.on_fallback = true;
+        let charged = std::mem::replace(&mut self.loadout, fallback);
+        if let Some(charges) = self.charges.as_mut() {
+            charges.spare = Some(charged);
+        }
+        true
+    }
+
+    // damage from every poison stack due this tick
+    pub fn poison_damage(&self, tick: usize) -> u32 {
+        stacks_due(&self.poisons, tick)
+    }
+
+    // what the stacks antipoison cured or kept off would have done this tick
+    pub fn poison_prevented(&self, tick: usize) -> u32 {
+        stacks_due(&self.cured_poisons, tick)
+    }
+
+    // hp a minute all the current stacks add up to
+    pub fn poison_rate(&self) -> u32 {
+        self.poisons.iter().map(|poison| poison.damage * 100 / poison.every as u32).sum()
+    }
+
+    pub fn apply_poison(&mut self, poison: Poison) {
+        if poison.since < self.poison_immune_until {
+            self.cured_poisons.push(poison);
+        } else {
+            self.poisons.push(poison);
+        }
+    }
+
+    pub fn drink_antipoison(&mut self, tick: usize, immunity: usize) {
+        self.cured_poisons.append(&mut self.poisons);
+        self.poison_immune_until = tick + immunity;
+    }
+
+    pub fn profile_key(&self) -> ProfileKey {
+        ProfileKey {
+            att_level: self.stats.att_level,
+            str_level: self.stats.str_level,
+            def_level: self.stats.def_level,
+            on_fallback: self.charges.as_ref().is_some_and(|charges| charges.on_fallback),
+            praying: self.prayer.is_on(),
+            loadout_generation: self.loadout_generation,
+        }
+    }
+
+    // back to how the player was built, full hp and prayer, empty pack, no poison, charged weapon
+    // in hand and any levels gained or gear equipped last trial dropped
+    pub fn reset_for_trial(&mut self) {
+        self.stats = self.base_stats.clone();
+        self.xp = Experience::for_stats(&self.stats);
+        self.inventory.clear();
+        self.inventory.reserved = 0;
+        self.cure();
+        self.recharge();
+        if self.loadout != self.base_loadout {
+            self.loadout = self.base_loadout.clone();
+            self.loadout_generation += 1;
+        }
+        self.energy = MAX_ENERGY;
+        self.prayer.restore();
+    }
+
+    // standing, fighting or walking, at 1 agility
+    pub fn regen_energy(&mut self, ticks: usize) {
+        self.energy = (self.energy + 8 * ticks as u32).min(MAX_ENERGY);
+    }
+
+    // ticks a trip that takes `run_ticks` at a run really takes, running till we're out of energy and
+    // walking the rest at half the pace. every kg we carry up to 64 drains faster, `worn` can go
+    // negative for weight-reducing gear
+    pub fn travel(&mut self, run_ticks: usize, worn: f64) -> usize {
+        let weight = (self.inventory.weight() + worn).clamp(0.0, 64.0);
+        let drain = 67 + (67.0 * weight / 64.0) as u32;
+        let running = run_ticks.min((self.energy / drain) as usize);
+        let walking = (run_ticks - running) * 2;
+        self.energy -= running as u32 * drain;
+        self.regen_energy(walking);
+        running + walking
+    }
+
+    pub fn level(&self, skill: Skill) -> u32 {
+        match skill {
+            Skill::Attack => self.stats.att_level,
+            Skill::Strength => self.stats.str_level,
+            Skill::Defence => self.stats.def_level,
+            Skill::Hitpoints => self.stats.hp_level,
+        }
+    }
+
+    // 4xp a damage to whatever the style trains and a third of that to hitpoints.
+    // TODO ranged has no skill of its own yet so it trains attack, or defence on longrange
+    pub fn gain_xp(&mut self, damage: u32) {
+        let xp = damage as f64 * 4.0;
+        let split = match &self.loadout {
+            Loadout::Melee(melee) => match melee.style {
+                MeleeStyle::Accurate => [1.0, 0.0, 0.0],
+                MeleeStyle::Aggressive => [0.0, 1.0, 0.0],
+                MeleeStyle::Defensive => [0.0, 0.0, 1.0],
+                MeleeStyle::Controlled => [1.0 / 3.0; 3],
+            },
+            Loadout::Ranged(ranged) => match ranged.style {
+                RangedStyle::Longrange => [0.0, 0.0, 1.0],
+                RangedStyle::Accurate | RangedStyle::Rapid => [1.0, 0.0, 0.0],
+            },
+            Loadout::Magic(_) => [0.0; 3],
+        };
+        self.xp.attack += xp * split[0];
+        self.xp.strength += xp * split[1];
+        self.xp.defence += xp * split[2];
+        self.xp.hitpoints += xp / 3.0;
+
+        self.stats.att_level = level_for_xp(self.xp.attack);
+        self.stats.str_level = level_for_xp(self.xp.strength);
+        self.stats.def_level = level_for_xp(self.xp.defence);
+        // a hitpoints level up comes with the extra hp
+        // configured above the curve (hp past 99 for a long session) it just stays put
+        let hp_level = level_for_xp(self.xp.hitpoints);
+        if hp_level > self.stats.hp_level {
+            self.stats.current_hp += hp_level - self.stats.hp_level;
+            self.stats.hp_level = hp_level;
+        }
+        self.stats.check_hp();
+    }
+
+    // banking cures everything, so nothing is being prevented any more either
+    pub fn cure(&mut self) {
+        self.poisons.clear();
+        self.cured_poisons.clear();
+        self.poison_immune_until = 0;
+    }
+
+    // back to full with the charged weapon in hand, returns the charges that took
+    pub fn recharge(&mut self) -> u32 {
+        let Some(charges) = self.charges.as_mut() else {
+            return 0;
+        };
+        let spent = charges.max - charges.left;
+        charges.left = charges.max;
+        if charges.on_fallback {
+            charges.on_fallback = false;
+            if let Some(charged) = charges.spare.take() {
+                let fallback = std::mem::replace(&mut self.loadout, charged);
+                if let Some(charges) = self.charges.as_mut() {
+                    charges.spare = Some(fallback);
+                }
+            }
+        }
+        spent
+    }
+
+    // the combat trait methods all read the active loadout, so a swap takes effect on the next attack.
+    // gives back the loadout taken off, or why the new one can't go on with the old one left in hand
+    pub fn swap_loadout(&mut self, loadout: Loadout) -> Result<Loadout, LoadoutError> {
+        loadout.check_rates()?;
+        self.loadout_generation += 1;
+        Ok(std::mem::replace(&mut self.loadout, loadout))
+    }
+
+    pub fn equip(&mut self, loadout: Loadout) -> Result<(), LoadoutError> {
+        self.swap_loadout(loadout).map(|_| ())
+    }
+}
+
+impl HasCombatStats for  Player {
+    fn is_dead(&self) -> bool {
+        self.stats.is_dead()
+    }
+
+    fn str_level(&self) -> u32 {
+        self.stats.str_level
+    }
+
+    fn att_level(&self) -> u32 {
+        self.stats.att_level
+    }
+
+    fn def_level(&self) -> u32 {
+        self.stats.def_level
+    }
+
+    fn deduct_hp(&mut self, amount: u32) {
+        self.stats.deduct_hp(amount)
+    }
+
+    fn is_npc(&self) -> bool {
+        false
+    }
+
+    fn is_player(&self) -> bool {
+        true
+    }
+
+    fn name(&self) -> &str {
+        "player"
+    }
+
+    fn attack_rate(&self) -> usize {
+        match &self.loadout {
+            Loadout::Melee(melee) => {
+                melee.rate
+            },
+            Loadout::Ranged(ranged) => {
+                ranged.attack_rate()
+            },
+            Loadout::Magic(magic) => {
+                magic.rate
+            },
+        }
+    }
+
+    fn equipment_accuracy(&self) -> u32 {
+        match &self.loadout {
+            Loadout::Melee(melee) => {
+                melee.accuracy
+            },
+            Loadout::Ranged(ranged) => {
+                ranged.accuracy
+            },
+            Loadout::Magic(magic) => {
+                magic.accuracy
+            },
+        }
+    }
+
+    fn equipment_strength(&self) -> u32 {
+        match &self.loadout {
+            Loadout::Melee(melee) => {
+                melee.str_bonus
+            },
+            Loadout::Ranged(ranged) => {
+                ranged.ammo_str
+            },
+            // spells hit up to their own max, see spell_max_hit
+            Loadout::Magic(_) => 0,
+        }
+    }
+
+    // gear bonuses are still a single number against whatever the mob hits with
+    fn defence(&self) -> DefenceProfile {
+        match &self.loadout {
+            Loadout::Melee(melee) => {
+                DefenceProfile::flat(melee.def_bonus)
+            },
+            Loadout::Ranged(ranged) => {
+                DefenceProfile::flat(ranged.def_bonus)
+            },
+            Loadout::Magic(magic) => {
+                DefenceProfile::flat(magic.def_bonus)
+            },
+        }
+    }
+
+    fn attack_type(&self) -> AttackType {
+        match &self.loadout {
+            Loadout::Melee(melee) => melee.attack_type,
+            Loadout::Ranged(_) => AttackType::Ranged,
+            Loadout::Magic(_) => AttackType::Magic,
+        }
+    }
+
+    fn style_bonuses(&self) -> [u32; 3] {
+        match &self.loadout {
+            Loadout::Melee(melee) => melee.style.style_bonuses(),
+            Loadout::Ranged(ranged) => ranged.style_bonuses(),
+            Loadout::Magic(_) => [1, 0, 0], // no stance to pick, spells roll off level + 9
+        }
+    }
+
+    fn spell_max_hit(&self) -> Option<u32> {
+        match &self.loadout {
+            Loadout::Magic(magic) => Some(magic.max_hit),
+            _ => None
+        }
+    }
+
+    fn prayer_boosts(&self) -> [f64; 3] {
+        self.prayer.boosts()
+    }
+}
+
+impl HasCombatStats for &mut Player {
+    fn is_dead(&self) -> bool {
+        self.stats.is_dead()
+    }
+
+    fn str_level(&self) -> u32 {
+        self.stats.str_level
+    }
+
+    fn att_level(&self) -> u32 {
+        self.stats.att_level
+    }
+
+    fn def_level(&self) -> u32 {
+        self.stats.def_level
+    }
+
+    fn deduct_hp(&mut self, amount: u32) {
+        self.stats.deduct_hp(amount)
+    }
+
+    fn is_npc(&self) -> bool {
+        false
+    }
+
+    fn is_player(&self) -> bool {
+        true
+    }
+
+    fn name(&self) -> &str {
+        "player"
+    }
+
+    fn attack_rate(&self) -> usize {
+        match &self.loadout {
+            Loadout::Melee(melee) => {
+                melee.rate
+            },
+            Loadout::Ranged(ranged) => {
+                ranged.attack_rate()
+            },
+            Loadout::Magic(magic) => {
+                magic.rate
+            },
+        }
+    }
+
+    fn equipment_accuracy(&self) -> u32 {
+        match &self.loadout {
+            Loadout::Melee(melee) => {
+                melee.accuracy
+            },
+            Loadout::Ranged(ranged) => {
+                ranged.accuracy
+            },
+            Loadout::Magic(magic) => {
+                magic.accuracy
+            },
+        }
+    }
+
+    fn equipment_strength(&self) -> u32 {
+        match &self.loadout {
+            Loadout::Melee(melee) => {
+                melee.str_bonus
+            },
+            Loadout::Ranged(ranged) => {
+                ranged.ammo_str
+            },
+            // spells hit up to their own max, see spell_max_hit
+            Loadout::Magic(_) => 0,
+        }
+    }
+
+    // gear bonuses are still a single number against whatever the mob hits with
+    fn defence(&self) -> DefenceProfile {
+        match &self.loadout {
+            Loadout::Melee(melee) => {
+                DefenceProfile::flat(melee.def_bonus)
+            },
+            Loadout::Ranged(ranged) => {
+                DefenceProfile::flat(ranged.def_bonus)
+            },
+            Loadout::Magic(magic) => {
+                DefenceProfile::flat(magic.def_bonus)
+            },
+        }
+    }
+
+    fn attack_type(&self) -> AttackType {
+        match &self.loadout {
+            Loadout::Melee(melee) => melee.attack_type,
+            Loadout::Ranged(_) => AttackType::Ranged,
+            Loadout::Magic(_) => AttackType::Magic,
+        }
+    }
+
+    fn style_bonuses(&self) -> [u32; 3] {
+        match &self.loadout {
+            Loadout::Melee(melee) => melee.style.style_bonuses(),
+            Loadout::Ranged(ranged) => ranged.style_bonuses(),
+            Loadout::Magic(_) => [1, 0, 0], // no stance to pick, spells roll off level + 9
+        }
+    }
+
+    fn spell_max_hit(&self) -> Option<u32> {
+        match &self.loadout {
+            Loadout::Magic(magic) => Some(magic.max_hit),
+            _ => None
+        }
+    }
+
+    fn prayer_boosts(&self) -> [f64; 3] {
+        self.prayer.boosts()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::*;
+
+    // a hitpoints level comes with its hp, and a level configured past the curve never drops
+    #[test]
+    fn hp_levels_only_go_up() {
+        let mut player = melee_player(bare_weapon(), CombatStats::full(1, 1, 1, 10));
+        player.deduct_hp(5);
+        player.gain_xp(300);
+        assert!(player.stats.hp_level > 10);
+        assert_eq!(player.stats.current_hp, player.stats.hp_level - 5);
+
+        let mut player = melee_player(bare_weapon(), CombatStats::full(1, 1, 1, 5000));
+        player.gain_xp(10);
+        assert_eq!((player.stats.hp_level, player.stats.current_hp), (5000, 5000));
+    }
+
+    // a loadout that can't swing is turned away wherever it's handed over, and a refused swap leaves
+    // the old weapon in hand
+    #[test]
+    fn bad_loadouts_are_errors() {
+        let stalled = Loadout::Melee(MeleeDps { rate: 0, ..bare_weapon() });
+        let built = Player::new(stalled.clone(), Inventory::default(), PlayerCoords::new(0, 0, 0), CombatStats::full(1, 1, 1, 10));
+        assert_eq!(built.err(), Some(LoadoutError::ZeroRate(stalled.clone())));
+        let charged = melee_player(bare_weapon(), CombatStats::full(1, 1, 1, 10))
+            .with_charges(WeaponCharges::new(10, 1, 0, Some(stalled.clone())));
+        assert_eq!(charged.err(), Some(LoadoutError::ZeroRate(stalled.clone())));
+
+        let mut player = melee_player(bare_weapon(), CombatStats::full(1, 1, 1, 10));
+        assert_eq!(player.equip(stalled.clone()), Err(LoadoutError::ZeroRate(stalled)));
+        assert_eq!(player.loadout, Loadout::Melee(bare_weapon()));
+        let sword = Loadout::Melee(MeleeDps { str_bonus: 10, ..bare_weapon() });
+        assert_eq!(player.swap_loadout(sword.clone()), Ok(Loadout::Melee(bare_weapon())));
+        assert_eq!(player.loadout, sword);
+    }
+
+    // everything a trial wears down or piles up, undone by a reset back to the player as built
+    #[test]
+    fn reset_player_is_fresh() {
+        let fresh = || melee_player(bare_weapon(), CombatStats::full(40, 40, 40, 40))
+            .with_charges(WeaponCharges::new(10, 1, 0, Some(Loadout::Melee(MeleeDps { rate: 6, ..bare_weapon() }))))
+            .expect("a valid fallback")
+            .with_prayers(Prayers::new(43, 5, vec![Prayer::ProtectFromMelee]));
+        let mut player = fresh();
+        player.deduct_hp(15);
+        player.gain_xp(5000);
+        player.apply_poison(Poison { damage: 2, every: 30, since: 0 });
+        player.drink_antipoison(10, 100);
+        player.apply_poison(Poison { damage: 2, every: 30, since: 200 });
+        while player.charges.as_ref().is_some_and(|charges| !charges.on_fallback) {
+            player.use_charges();
+        }
+        player.prayer.drain(10000);
+        player.travel(500, 0.0);
+        let _ = player.inventory.add_item(Item::new("coins", 100));
+        player.inventory.reserved = 20;
+        assert_ne!(player, fresh());
+
+        player.reset_for_trial();
+        assert_eq!(player, fresh());
+    }
+}
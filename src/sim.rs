@@ -0,0 +1,1224 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{self, AtomicUsize};
+use std::time::Instant;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::Deserialize;
+use crate::combat::*;
+use crate::config::*;
+use crate::inventory::*;
+use crate::loot::*;
+use crate::mob::*;
+use crate::player::*;
+use crate::summary::*;
+
+// what ends a trial
+// in a sim file: goal = "talisman", or a table like { session = 60000 } (ticks, 6000 an hour),
+// { level = ["attack", 60] }, { item = "rune_spear" } or { kills = 1000 }
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Goal {
+    Talisman, // keep going until the nature talisman drops
+    Session(usize), // play for a fixed number of ticks, drop or not
+    Level(Skill, u32), // train until the skill gets there, needs gain_levels
+    Item(String), // keep going until we've picked one of these up
+    Kills(usize), // a slayer task or collection log count, drops don't matter
+}
+
+// how the search results get printed, format = "csv" in a sim file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    #[default]
+    Pretty,
+    Csv, // one row per mob under a single header, for spreadsheets
+}
+
+// a whole setup can come from a sim file (versioned like the mob files). anything left out keeps
+// the default below, and the command line flags still override whatever the file says
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SimConfig {
+    // chance the player clicks on time for any given attack, 1.0 is perfect play
+    pub efficiency: f64,
+    pub loot_pickup_ticks: usize, // time spent picking up each drop we keep
+    pub min_loot_value: u64, // drops worth less than this are left on the floor
+    // a drop that won't fit and is worth at least this sends us to the bank early. None never does, so
+    // by default a full pack leaves drops on the floor rather than walking off for them: the talisman
+    // hunts this is built for lose more to the walk than the junk is worth. the talisman itself
+    // makes room instead, see loot_drop
+    pub bank_for_loot: Option<u64>,
+    // a drop worth at least this gets taken straight to the bank, None never does
+    pub secure_rare_value: Option<u64>,
+    #[serde(skip)]
+    pub prices: Arc<dyn PriceSource>, // --prices, a file can't pick a price source
+    pub regen: bool, // passive 1hp a minute, off is handy for checking damage math
+    // food withdrawn each bank trip and how many of each, every piece takes a slot loot can't use
+    // in a sim file a list of { name, heal, count }
+    #[serde(deserialize_with = "food_list")]
+    pub food: Vec<(FoodKind, usize)>,
+    // trips' worth of that food waiting in the bank, None never runs out. a trial that can't
+    // withdraw a full trip's worth ends there and counts as failed, same as dying
+    pub bank_food: Option<usize>,
+    // ticks at a spot before aggressive mobs get used to us, 10 minutes like the game. None means they never do
+    pub aggression_timeout: Option<usize>,
+    // pick where in their attack cycles both sides start each fight instead of always 0 and 1
+    pub random_start_tick: bool,
+    pub eat_threshold: Option<u32>, // eat when hp drops below this, None is 20 under max hp
+    pub eat_delay: usize, // ticks eating pushes our next swing back, 0 eats between swings for free
+    pub gain_levels: bool, // xp from our hits levels us up mid-trial, off keeps the player as configured
+    pub run_energy: bool, // bank trips slow to a walk once energy runs out, off always runs
+    pub worn_weight: f64, // kg of equipped gear, negative with weight-reducing gear
+    #[serde(skip)]
+    pub progress: Option<Arc<Progress>>, // trial counter for long runs, stderr only
+    pub antipoison: Option<Antipoison>, // None tanks every poison with food
+    pub auto_note: HashSet<String>, // drops that land noted, so a whole stack takes one slot
+    pub goal: Goal,
+    #[serde(skip)]
+    pub drop_rule: Option<DropRule>, // None takes every talisman the tables give
+    // stop a search once the 95% interval on the mean hours is within this fraction of it, None runs every trial
+    pub converge: Option<f64>,
+    pub drop_rate_multiplier: f64, // scales the gem table odds, 2.0 for a double drop rate weekend
+    pub double_drops: bool, // every kill rolls its drops twice
+    pub sequential: bool, // run the trials one after another on this thread, for debugging
+    pub format: OutputFormat,
+    pub histogram: bool, // bucket the successful trials' hours in the printed summary
+}
+
+// perfect clicking, looting everything instantly, regen on, a full inventory of salmon eaten 20 under
+// max hp, no banking early for loot or rares, running everywhere without energy, aggressive mobs
+// losing interest after 10 minutes, hunting the talisman. eating costs its 3 ticks like the game, and
+// with the aggression timeout that's longer hours than runs from before either went in, --eat-delay 0
+// and --aggression-timeout never put them back
+impl Default for SimConfig {
+    fn default() -> Self {
+        Self {
+            efficiency: 1.0,
+            loot_pickup_ticks: 0,
+            min_loot_value: 0,
+            bank_for_loot: None,
+            secure_rare_value: None,
+            prices: Arc::new(StaticPriceSource::default()),
+            regen: true,
+            food: vec![(FoodKind::salmon(), INVENTORY_SIZE)],
+            bank_food: None,
+            aggression_timeout: Some(1000),
+            random_start_tick: false,
+            eat_threshold: None,
+            eat_delay: 3,
+            gain_levels: false,
+            run_energy: false,
+            worn_weight: 0.0,
+            progress: None,
+            antipoison: None,
+            auto_note: HashSet::new(),
+            goal: Goal::Talisman,
+            drop_rule: None,
+            converge: None,
+            drop_rate_multiplier: 1.0,
+            double_drops: false,
+            sequential: false,
+            format: OutputFormat::Pretty,
+            histogram: false,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct FoodSpec {
+    pub name: String,
+    pub heal: u32,
+    pub count: usize,
+}
+
+pub fn food_list<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Vec<(FoodKind, usize)>, D::Error> {
+    let specs = Vec::<FoodSpec>::deserialize(deserializer)?;
+    Ok(specs.into_iter().map(|spec| (FoodKind::new(&spec.name, spec.heal), spec.count)).collect())
+}
+
+impl SimConfig {
+    pub fn from_path(path: &Path) -> Result<Self, ConfigError> {
+        read_config(path)
+    }
+
+    pub fn food_per_trip(&self) -> usize {
+        self.food.iter().map(|(_, count)| count).sum()
+    }
+
+    pub fn allows_drop(&self, item: &Item, kill: &KillContext) -> bool {
+        item.name != "nature_talisman" || self.drop_rule.as_ref().is_none_or(|rule| rule.allows(kill))
+    }
+
+    pub fn drop_rolls(&self) -> usize {
+        if self.double_drops { 2 } else { 1 }
+    }
+
+    pub fn antipoison_slots(&self) -> usize {
+        self.antipoison.map_or(0, |antipoison| antipoison.per_trip)
+    }
+
+    pub fn eat_below(&self, hp_level: u32) -> u32 {
+        self.eat_threshold.unwrap_or(hp_level.saturating_sub(20))
+    }
+
+    pub fn value_of(&self, item: &Item) -> u64 {
+        item_value(item, self.prices.as_ref())
+    }
+
+    pub fn loot_value(&self, loot: &HashMap<String, u64>) -> u64 {
+        loot.iter().map(|(name, total)| self.value_of(&Item::new(name, *total as usize))).sum()
+    }
+
+    // looted items the price table doesn't know, they count as 0 so gp/hr comes out low
+    pub fn unpriced(&self, loot: &HashMap<String, u64>) -> Vec<String> {
+        let mut names: Vec<String> = loot.keys()
+            .filter(|name| *name != "coins" && self.prices.price(name).is_none())
+            .cloned()
+            .collect();
+        names.sort();
+        names
+    }
+}
+
+#[derive(Debug, PartialEq)]
+// every tick of a trial lands in exactly one of the ticks_ buckets besides ticks_lost,
+// which is a share of the combat ticks
+pub struct TallyReport {
+    pub food_eaten: u32,
+    pub food_eaten_by_kind: HashMap<String, u32>,
+    pub ticks_in_combat: usize, // at the spot with something to fight
+    pub ticks_between_trips: usize, // away banking
+    pub ticks_waiting_for_spawn: usize, // at the spot with everything dead
+    pub ticks_lost: usize,
+    pub ticks_looting: usize,
+    pub food_banks: usize, // trips because we ran out of food
+    pub loot_banks: usize, // trips because a drop didn't fit
+    pub charge_banks: usize, // trips because the weapon ran dry with no fallback
+    pub rare_banks: usize, // trips to secure a valuable drop
+    pub charges_recharged: u64,
+    pub recoil_damage: u32, // taken from the mob's on hit effects
+    pub poison_damage: u32,
+    pub poison_prevented: u32, // what cured or resisted stacks would have done before the next bank
+    pub antipoison_doses: u32,
+    pub drops_skipped: usize,
+    pub drops_no_space: usize,
+    pub times_aggroed: usize, // fights an aggressive mob opened instead of us
+    pub kills: usize,
+    pub got_drop: bool,
+    pub loot: HashMap<String, usize>, // every drop we picked up this trial, gems and coins included
+    pub tiers: HashMap<LootTier, (usize, u64)>, // (drops, gp) seen per tier, looted or not
+    pub regen_marks: usize, // minute marks on the clock that have already healed us
+}
+
+impl Default for TallyReport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TallyReport {
+    pub fn new() -> Self {
+        Self {
+            food_eaten: 0,
+            food_eaten_by_kind: HashMap::new(),
+            ticks_in_combat: 0,
+            ticks_between_trips: 0,
+            ticks_waiting_for_spawn: 0,
+            ticks_lost: 0,
+            ticks_looting: 0,
+            food_banks: 0,
+            loot_banks: 0,
+            charge_banks: 0,
+            rare_banks: 0,
+            charges_recharged: 0,
+            recoil_damage: 0,
+            poison_damage: 0,
+            poison_prevented: 0,
+            antipoison_doses: 0,
+            drops_skipped: 0,
+            drops_no_space: 0,
+            times_aggroed: 0,
+            kills: 0,
+            got_drop: false,
+            loot: HashMap::new(),
+            tiers: HashMap::new(),
+            regen_marks: 0,
+        }
+    }
+
+    // the minute marks up to `clock` that haven't healed us yet, so each heals once whether it fell
+    // in a fight, a walk to the bank or looting
+    pub fn regen_due(&mut self, clock: usize) -> u32 {
+        let marks = clock / 100;
+        let due = marks.saturating_sub(self.regen_marks);
+        self.regen_marks = self.regen_marks.max(marks);
+        due as u32
+    }
+
+    pub fn bank_for_food(&mut self, ticks_till_return: usize) {
+        self.ticks_between_trips += ticks_till_return;
+        self.food_banks += 1;
+    }
+
+    pub fn bank_for_loot(&mut self, ticks_till_return: usize) {
+        self.ticks_between_trips += ticks_till_return;
+        self.loot_banks += 1;
+    }
+
+    pub fn bank_for_rare(&mut self, ticks_till_return: usize) {
+        self.ticks_between_trips += ticks_till_return;
+        self.rare_banks += 1;
+    }
+
+    pub fn bank_for_charges(&mut self, ticks_till_return: usize) {
+        self.ticks_between_trips += ticks_till_return;
+        self.charge_banks += 1;
+    }
+
+    pub fn recharge(&mut self, charges: u32) {
+        self.charges_recharged += charges as u64;
+    }
+
+    pub fn recoil(&mut self, damage: u32) {
+        self.recoil_damage += damage;
+    }
+
+    pub fn poisoned(&mut self, damage: u32) {
+        self.poison_damage += damage;
+    }
+
+    pub fn drink_antipoison(&mut self) {
+        self.antipoison_doses += 1;
+    }
+
+    pub fn eat(&mut self, food: &FoodKind) {
+        self.food_eaten += 1;
+        *self.food_eaten_by_kind.entry(food.name.clone()).or_insert(0) += 1;
+    }
+
+    pub fn fight_tick(&mut self) {
+        self.ticks_in_combat += 1;
+    }
+
+    pub fn wait_for_spawn(&mut self) {
+        self.ticks_waiting_for_spawn += 1;
+    }
+
+    pub fn kill(&mut self) {
+        self.kills += 1;
+    }
+
+    pub fn loot(&mut self, item: &Item) {
+        *self.loot.entry(item.name.clone()).or_insert(0) += item.quantity;
+    }
+
+    // something picked up earlier went back on the floor
+    pub fn unloot(&mut self, item: &Item) {
+        if let Some(quantity) = self.loot.get_mut(&item.name) {
+            *quantity = quantity.saturating_sub(item.quantity);
+            if *quantity == 0 {
+                self.loot.remove(&item.name);
+            }
+        }
+    }
+
+    pub fn see_drop(&mut self, tier: LootTier, value: u64) {
+        let (drops, gp) = self.tiers.entry(tier).or_insert((0, 0));
+        *drops += 1;
+        *gp += value;
+    }
+
+    pub fn pick_up(&mut self, ticks: usize) {
+        self.ticks_looting += ticks;
+    }
+
+    pub fn skip_drop(&mut self) {
+        self.drops_skipped += 1;
+    }
+
+    pub fn aggro(&mut self) {
+        self.times_aggroed += 1;
+    }
+
+    pub fn no_space(&mut self) {
+        self.drops_no_space += 1;
+    }
+
+    pub fn lose_tick(&mut self) {
+        self.ticks_lost += 1;
+    }
+
+    // total time the trial took
+    pub fn to_ticks(&self) -> usize {
+        self.ticks_in_combat + self.ticks_between_trips + self.ticks_waiting_for_spawn + self.ticks_looting
+    }
+}
+
+// things that happen mid-trial, for anything that wants more than the final report
+#[derive(Debug, Clone)]
+pub enum SimEvent {
+    Tick { tick: usize }, // each tick at the spot, the bank trips and looting are counted separately
+    Kill { tick: usize },
+    Drop { tick: usize, item: Item },
+    Ate { tick: usize },
+    Banked { tick: usize },
+    Died { tick: usize, elapsed: usize }, // elapsed counts bank trips and looting too
+    OutOfFood { tick: usize, elapsed: usize }, // the bank hadn't another trip's worth
+}
+
+// the weakest food that still gets us back over the threshold, so sharks aren't wasted on chip
+// damage, otherwise the biggest heal we have left. None once the trip's food is gone
+pub fn pick_food(food: &[(FoodKind, usize)], food_left: &[usize], current_hp: u32, eat_below: u32) -> Option<usize> {
+    let available = || (0..food.len()).filter(|i| food_left[*i] > 0);
+    available()
+        .filter(|i| current_hp + food[*i].0.heal >= eat_below)
+        .min_by_key(|i| food[*i].0.heal)
+        .or_else(|| available().max_by_key(|i| food[*i].0.heal))
+}
+
+// deposit the loot, recharge, top up prayer and head straight back, the food we're carrying stays as it is.
+// the caller records why we went, returns how long the trip took
+pub fn quick_bank_trip(player: &mut Player, bank: &mut Bank, mob: &mut RollsGemtable, config: &SimConfig, report: &mut TallyReport) -> usize {
+    let trip = if config.run_energy {
+        player.travel(mob.ticks_between_trips, config.worn_weight)
+    } else {
+        mob.ticks_between_trips
+    };
+    player.inventory.bank(bank);
+    report.recharge(player.recharge());
+    player.cure();
+    player.prayer.restore();
+    if config.regen {
+        // however many minute marks the walk passes, the regen clock doesn't reset at the bank
+        player.stats.heal_hp(report.regen_due(report.to_ticks() + trip));
+    }
+    if !mob.is_dead() {
+        mob.stats.heal_hp(99); // mob regens while we're gone
+    }
+    trip
+}
+
+// the player's swing on this tick, if it's due. a hit comes off the mob, earns xp when we're levelling
+// and sets off whatever the mob does to those that hit it, returned with any recoil it cost us.
+// run_trial and fight_to_death both swing through here, so a fight means the same thing to either
+pub fn player_swing(tick: usize, player_start: usize, player: &mut Player, mob: &mut RollsGemtable, profiles: &mut MatchupProfiles, config: &SimConfig, rng: &mut impl Rng) -> (HitOutcome, u32) {
+    profiles.refresh(player, mob);
+    let outcome = run_combat_tick(tick, player_start, &profiles.player, rng);
+    let mut recoil = 0;
+    if outcome.hit {
+        sim_debug!("{} hits {} for {} at tick {tick}", player.name(), mob.name(), outcome.damage);
+        mob.deduct_hp(outcome.damage);
+        if config.gain_levels {
+            player.gain_xp(outcome.damage);
+        }
+        match mob.on_player_hit {
+            Some(Effect::Recoil { damage }) => {
+                player.deduct_hp(damage);
+                recoil = damage;
+            },
+            Some(Effect::Poison { damage, every }) => {
+                player.apply_poison(Poison { damage, every: every.max(1), since: tick });
+            },
+            None => {},
+        }
+    }
+    (outcome, recoil)
+}
+
+// the mob's swing on this tick, if it's due and lands, comes off the player less whatever their prayers
+// take off it. the damage it did, if any
+pub fn mob_swing(tick: usize, mob_start: usize, player: &mut Player, mob: &RollsGemtable, profiles: &mut MatchupProfiles, rng: &mut impl Rng) -> Option<u32> {
+    profiles.refresh(player, mob);
+    let outcome = run_combat_tick(tick, mob_start, &profiles.mob, rng);
+    if !outcome.hit {
+        return None
+    }
+    let protection = player.prayer.protection(mob.attack_type());
+    let damage = (outcome.damage as f64 * (1.0 - protection)).floor() as u32;
+    sim_debug!("{} hits {} for {damage} at tick {tick}", mob.name(), player.name());
+    player.deduct_hp(damage);
+    Some(damage)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FightOutcome {
+    pub player_died: bool,
+    pub ticks: usize,
+    pub player_damage: u32, // dealt by the player
+    pub mob_damage: u32, // dealt by the mob
+}
+
+// one mob, toe to toe until one of them drops, swinging the way a trial does under `config`. no eating,
+// banking or poison ticking, the player's on tick 0 and the mob's a tick behind unless it's aggressive,
+// when it's on tick 0 too and gets its swing in first
+pub fn fight_to_death(player: &mut Player, mob: &mut RollsGemtable, config: &SimConfig, rng: &mut impl Rng) -> FightOutcome {
+    let mut profiles = MatchupProfiles::new(player, mob);
+    let mob_start = if mob.aggressive { 0 } else { 1 % mob.attack_rate };
+    let mut outcome = FightOutcome { player_died: false, ticks: 0, player_damage: 0, mob_damage: 0 };
+    while !player.is_dead() && !mob.is_dead() {
+        let tick = outcome.ticks;
+        outcome.ticks += 1;
+        // overkill isn't damage dealt, so it's counted off what each side had left
+        let mob_first = mob.aggressive && tick == 0;
+        if mob_first {
+            let player_hp = player.stats.current_hp;
+            mob_swing(tick, mob_start, player, mob, &mut profiles, rng);
+            outcome.mob_damage += player_hp - player.stats.current_hp;
+            if player.is_dead() {
+                break
+            }
+        }
+        let mob_hp = mob.stats.current_hp;
+        player_swing(tick, 0, player, mob, &mut profiles, config, rng);
+        outcome.player_damage += mob_hp - mob.stats.current_hp;
+        if mob.is_dead() || player.is_dead() || mob_first {
+            continue
+        }
+        let player_hp = player.stats.current_hp;
+        mob_swing(tick, mob_start, player, mob, &mut profiles, rng);
+        outcome.mob_damage += player_hp - player.stats.current_hp;
+    }
+    outcome.player_died = player.is_dead();
+    outcome
+}
+
+pub fn search_talisman<F>(base_mob: &RollsGemtable, context: &GameContext, config: &SimConfig, rng: &mut impl Rng, on_event: &mut F) -> Option<TallyReport>
+where F: FnMut(SimEvent) {
+    let mut player = context.player.clone();
+    let mut profiles = MatchupProfiles::new(&player, base_mob);
+    run_trial(base_mob, context, &mut player, &mut profiles, config, rng, on_event)
+}
+
+// one trial for the given player, who keeps whatever levels they gained
+pub fn run_trial<F>(base_mob: &RollsGemtable, context: &GameContext, player: &mut Player, profiles: &mut MatchupProfiles, config: &SimConfig, rng: &mut impl Rng, on_event: &mut F) -> Option<TallyReport>
+where F: FnMut(SimEvent) {
+    let mut mob = (*base_mob).clone();
+    let mut live_mobs = base_mob.available_npcs;
+    let mut respawns = VecDeque::new(); // ticks the mobs we've killed come back on, soonest first
+    let mut player_start = 0; // shifts whenever a reaction-lag tick is lost or we eat
+    let mut swings_from = 0; // no swinging before this tick, the tail end of eating
+    let mut mob_start = 1 % mob.attack_rate; // takes mob a tick to respond
+    let mut arrived_on = 0; // aggression tolerance counts from when we got to the spot
+    let mut aggro_on = None; // the tick an aggressive mob came at us, it swings before we do on that one
+    let mut report = TallyReport::new();
+    let mut bank = Bank::default();
+    let potions_per_trip = config.antipoison_slots().min(player.inventory.capacity());
+    let food_room = player.inventory.capacity() - potions_per_trip;
+    // potions come out of the food's slots, the last kinds listed give way first
+    let restock = || {
+        let mut room = food_room;
+        config.food.iter()
+            .map(|(_, count)| {
+                let take = (*count).min(room);
+                room -= take;
+                take
+            })
+            .collect::<Vec<usize>>()
+    };
+    let mut food_left = restock();
+    if let Some(trips) = config.bank_food {
+        for ((food, _), take) in config.food.iter().zip(&food_left) {
+            bank.store(&Item::new(&food.name, take * trips));
+        }
+    }
+    let food_per_trip = config.food_per_trip().min(food_room);
+    let doses_per_trip = config.antipoison.map_or(0, |antipoison| antipoison.doses * potions_per_trip as u32);
+    let mut doses_left = doses_per_trip;
+    player.inventory.reserved = food_per_trip + potions_per_trip;
+    if config.random_start_tick {
+        player_start = rng.random_range(0..player.attack_rate());
+        mob_start = rng.random_range(0..mob.attack_rate);
+    }
+    if mob.aggressive {
+        mob_start = 0;
+        aggro_on = Some(0);
+        report.aggro();
+    }
+    sim_debug!("fighting {} at tick 0", mob.name);
+
+    let mut ticks_at_spot = 0; // loop iterations, to check the bookkeeping against
+    'trial: for (tick, _) in (0..1).cycle().enumerate() {
+        // every minute we heal 1 hp. the clock counts from when we first arrive, full hp with a full
+        // inventory, and keeps running through bank trips so later trips start wherever it's got to.
+        // ahead of the goal so the mark a session ends on still counts
+        if config.regen {
+            player.stats.heal_hp(report.regen_due(report.to_ticks()));
+        }
+        match &config.goal {
+            Goal::Session(budget) if report.to_ticks() >= *budget => break,
+            Goal::Level(skill, level) if player.level(*skill) >= *level => {
+                report.got_drop = true;
+                break;
+            },
+            // checked the tick after, so the last kill's drops are already looted
+            Goal::Kills(kills) if report.kills >= *kills => {
+                report.got_drop = true;
+                break;
+            },
+            _ => {},
+        }
+        ticks_at_spot += 1;
+        on_event(SimEvent::Tick { tick });
+        if config.run_energy {
+            player.regen_energy(1);
+        }
+        player.prayer.drain(1);
+        if let Some(antipoison) = config.antipoison {
+            if doses_left > 0 && player.poison_rate() >= antipoison.cure_at.max(1) {
+                player.drink_antipoison(tick, antipoison.immunity);
+                doses_left -= 1;
+                if doses_left.is_multiple_of(antipoison.doses) {
+                    // that potion's empty, its slot goes back to loot
+                    player.inventory.reserved = player.inventory.reserved.saturating_sub(1);
+                }
+                report.drink_antipoison();
+            }
+        }
+        let eat_below = config.eat_below(player.stats.hp_level);
+        if player.stats.current_hp < eat_below {
+            // we need to bank
+            if food_left.iter().all(|left| *left == 0) {
+                let trip = quick_bank_trip(player, &mut bank, &mut mob, config, &mut report);
+                report.bank_for_food(trip);
+                food_left = restock();
+                if config.bank_food.is_some() {
+                    let withdrawn: Option<Vec<usize>> = config.food.iter().zip(&food_left)
+                        .map(|((food, _), take)| bank.withdraw(&food.name, *take).map(|item| item.quantity))
+                        .collect();
+                    let Some(withdrawn) = withdrawn else {
+                        sim_debug!("out of food in the bank at tick {tick}");
+                        on_event(SimEvent::OutOfFood { tick, elapsed: report.to_ticks() });
+                        return None
+                    };
+                    food_left = withdrawn;
+                }
+                doses_left = doses_per_trip;
+                player.inventory.reserved = food_per_trip + potions_per_trip;
+                player.stats.heal_hp(99); // assume we heal up before coming out
+                arrived_on = tick;
+                sim_debug!("banked at tick {tick}");
+                on_event(SimEvent::Banked { tick });
+            }
+            // coming back from the bank we're topped up already
+            let pick = if player.stats.current_hp < eat_below {
+                pick_food(&config.food, &food_left, player.stats.current_hp, eat_below)
+            } else {
+                None
+            };
+            if let Some(i) = pick {
+                let food = &config.food[i].0;
+                player.stats.heal_hp(food.heal);
+                player.inventory.reserved = player.inventory.reserved.saturating_sub(1);
+                if config.eat_delay > 0 {
+                    // the swing we had coming lands eat_delay ticks late, and the rest follow on from it
+                    let rate = player.attack_rate();
+                    let next_swing = tick + (player_start + rate - tick % rate) % rate;
+                    swings_from = next_swing + config.eat_delay;
+                    player_start = swings_from % rate;
+                }
+                food_left[i] -= 1;
+                report.eat(food);
+                on_event(SimEvent::Ate { tick });
+            }
+        }
+        while respawns.front().is_some_and(|due| *due <= tick) {
+            respawns.pop_front();
+            live_mobs += 1;
+        }
+        // our last target's dead, move on to one that's standing or the first to respawn
+        if mob.is_dead() && live_mobs > 0 {
+            mob = base_mob.clone();
+            let tolerated = config.aggression_timeout.is_some_and(|timeout| tick - arrived_on >= timeout);
+            if mob.aggressive && !tolerated {
+                // it's on us the moment it spawns or we're free
+                mob_start = tick % mob.attack_rate;
+                aggro_on = Some(tick);
+                report.aggro();
+            } else if config.random_start_tick {
+                mob_start = rng.random_range(0..mob.attack_rate);
+            } else {
+                mob_start = 1 % mob.attack_rate;
+            }
+            if config.random_start_tick {
+                player_start = rng.random_range(0..player.attack_rate());
+            }
+            sim_debug!("fighting {} at tick {tick}", mob.name);
+        }
+        // poison keeps ticking whether or not there's anything to fight
+        report.poison_prevented += player.poison_prevented(tick);
+        let poison = player.poison_damage(tick);
+        if poison > 0 {
+            player.deduct_hp(poison);
+            report.poisoned(poison);
+            if player.is_dead() {
+                sim_debug!("died to poison at tick {tick}");
+                on_event(SimEvent::Died { tick, elapsed: report.to_ticks() });
+                return None
+            }
+        }
+        if live_mobs == 0 {
+            report.wait_for_spawn();
+            continue; // idle
+        }
+        report.fight_tick();
+        // a weapon swap can leave us on a slower or faster weapon than the swing timer assumes
+        player_start %= player.attack_rate();
+        let mob_first = aggro_on == Some(tick);
+        if mob_first {
+            mob_swing(tick, mob_start, player, &mob, profiles, rng);
+        }
+        if tick < swings_from {
+            // still eating
+        } else if config.efficiency < 1.0
+            && tick % player.attack_rate() == player_start
+            && rng.random::<f64>() >= config.efficiency {
+            // missed the click, the swing (and every one after it) slides back a tick
+            player_start = (player_start + 1) % player.attack_rate();
+            report.lose_tick();
+        } else if !player.is_dead() {
+            let (outcome, recoil) = player_swing(tick, player_start, player, &mut mob, profiles, config, rng);
+            report.recoil(recoil);
+            if outcome.attacked && !player.use_charges() {
+                let trip = quick_bank_trip(player, &mut bank, &mut mob, config, &mut report);
+                report.bank_for_charges(trip);
+                arrived_on = tick;
+                sim_debug!("banked to recharge at tick {tick}");
+                on_event(SimEvent::Banked { tick });
+            }
+        }
+        // whoever dropped on the player's swing doesn't get to swing back
+        if !mob_first && !mob.is_dead() && !player.is_dead() {
+            mob_swing(tick, mob_start, player, &mob, profiles, rng);
+        }
+        if player.is_dead() {
+            sim_debug!("died to {} at tick {tick}", mob.name);
+            on_event(SimEvent::Died { tick, elapsed: report.to_ticks() });
+            return None
+        }
+        if mob.is_dead() {
+            report.kill();
+            sim_debug!("killed {} at tick {tick}", mob.name);
+            on_event(SimEvent::Kill { tick });
+            let kill = KillContext {
+                mob: &mob.name,
+                kill: report.kills,
+                combat_level: player.stats.combat_level(),
+                is_members: context.is_members,
+                coordz: context.coordz(),
+            };
+            let drops: Vec<Item> = (0..config.drop_rolls())
+                .flat_map(|_| roll_kill_drops(&mob, context, config.drop_rate_multiplier, rng))
+                .filter(|item| config.allows_drop(item, &kill))
+                .collect();
+            for item in drops {
+                let value = config.value_of(&item);
+                report.see_drop(item.tier, value);
+                let mut result = loot_drop(&item, &mut player.inventory, config, &mut report);
+                if result == LootResult::NeedsBank {
+                    let trip = quick_bank_trip(player, &mut bank, &mut mob, config, &mut report);
+                    report.bank_for_loot(trip);
+                    arrived_on = tick;
+                    sim_debug!("banked for {} at tick {tick}", item.name);
+                    on_event(SimEvent::Banked { tick });
+                    result = loot_drop(&item, &mut player.inventory, config, &mut report);
+                }
+                let found = item.name == "nature_talisman" && result == LootResult::Taken;
+                let wanted = matches!(&config.goal, Goal::Item(name) if *name == item.name) && result == LootResult::Taken;
+                on_event(SimEvent::Drop { tick, item });
+                if found {
+                    report.got_drop = true;
+                    if config.goal == Goal::Talisman {
+                        break 'trial;
+                    }
+                }
+                if wanted {
+                    report.got_drop = true;
+                    break 'trial;
+                }
+                if result == LootResult::Taken && config.secure_rare_value.is_some_and(|min| value >= min) {
+                    // too valuable to risk carrying around, straight to the bank with it
+                    let trip = quick_bank_trip(player, &mut bank, &mut mob, config, &mut report);
+                    report.bank_for_rare(trip);
+                    arrived_on = tick;
+                    sim_debug!("banked to secure a rare at tick {tick}");
+                    on_event(SimEvent::Banked { tick });
+                }
+            }
+            live_mobs -= 1;
+            respawns.push_back(tick + mob.respawn_rate);
+        }
+
+    }
+    debug_assert_eq!(report.ticks_in_combat + report.ticks_waiting_for_spawn, ticks_at_spot,
+        "every tick at the spot should be either fighting or waiting on a spawn");
+    Some(report)
+}
+
+// one leg of a grind, fight this mob until the goal is met
+#[derive(Debug, Clone)]
+pub struct Phase {
+    pub mob: RollsGemtable,
+    pub goal: Goal,
+}
+
+// phases run back to back on the same player, so levels from training carry into the farming
+#[derive(Debug, Clone)]
+pub struct Plan {
+    pub phases: Vec<Phase>,
+}
+
+#[derive(Debug)]
+pub struct PlanReport {
+    pub phases: Vec<(String, TallyReport)>,
+}
+
+impl PlanReport {
+    pub fn to_ticks(&self) -> usize {
+        self.phases.iter().map(|(_, report)| report.to_ticks()).sum()
+    }
+
+    pub fn loot(&self) -> HashMap<String, usize> {
+        let mut loot = HashMap::new();
+        for (_, report) in &self.phases {
+            for (name, quantity) in &report.loot {
+                *loot.entry(name.clone()).or_insert(0) += quantity;
+            }
+        }
+        loot
+    }
+}
+
+// None if we die somewhere along the way
+pub fn run_plan(plan: &Plan, context: &GameContext, config: &SimConfig, rng: &mut impl Rng) -> Option<PlanReport> {
+    let mut player = context.player.clone();
+    let mut phases = Vec::new();
+    for phase in &plan.phases {
+        let config = SimConfig { goal: phase.goal.clone(), gain_levels: true, ..config.clone() };
+        let mut profiles = MatchupProfiles::new(&player, &phase.mob);
+        let report = run_trial(&phase.mob, context, &mut player, &mut profiles, &config, rng, &mut |_| {})?;
+        sim_debug!("{} phase done after {} ticks", phase.mob.name, report.to_ticks());
+        phases.push((phase.mob.name.clone(), report));
+    }
+    Some(PlanReport { phases })
+}
+
+// every trial gets its own stream, so any one of them can be rerun from the seed and its index
+pub fn trial_rng(seed: u64, trial: usize) -> StdRng {
+    StdRng::seed_from_u64(seed ^ (trial as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15))
+}
+
+// everything that happened in one trial, in order
+#[derive(Debug, Default)]
+pub struct Trace {
+    pub events: Vec<SimEvent>,
+}
+
+impl Trace {
+    pub fn print(&self) {
+        for event in &self.events {
+            println!("    {event:?}");
+        }
+    }
+}
+
+// reruns trial `trial` of a seeded search exactly, keeping every event
+pub fn replay_trial(mob: &RollsGemtable, context: &GameContext, config: &SimConfig, seed: u64, trial: usize) -> (Option<TallyReport>, Trace) {
+    let mut trace = Trace::default();
+    let report = search_talisman(mob, context, config, &mut trial_rng(seed, trial), &mut |event| trace.events.push(event));
+    (report, trace)
+}
+
+// trials finished across a whole run, atomic so searches running side by side can share it.
+// only ever goes to stderr so it never mixes into the results
+#[derive(Debug)]
+pub struct Progress {
+    pub done: AtomicUsize,
+    pub total: usize,
+    pub started: Instant,
+}
+
+impl Progress {
+    pub fn new(total: usize) -> Self {
+        Self {
+            done: AtomicUsize::new(0),
+            total,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn trial_done(&self, mob: &str, trial: usize, trials: usize) {
+        self.advance(1, mob, trial, trials);
+    }
+
+    // prints about every 1% of the run, overwriting the last line. a search that stops early
+    // advances by everything it skipped
+    pub fn advance(&self, by: usize, mob: &str, trial: usize, trials: usize) {
+        let before = self.done.fetch_add(by, atomic::Ordering::Relaxed);
+        let done = before + by;
+        let step = (self.total / 100).max(1);
+        if done / step == before / step && done < self.total {
+            return;
+        }
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let eta = elapsed / done as f64 * self.total.saturating_sub(done) as f64;
+        eprint!("\r{:>3}% {mob}: {}/{trials} trials, eta {eta:.0}s    ", (done * 100 / self.total.max(1)).min(100), trial + 1);
+        if done >= self.total {
+            eprintln!();
+        }
+    }
+}
+
+// mean and variance as the trials come in (welford's method), to tell when more won't help
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RunningMean {
+    pub count: usize,
+    pub mean: f64,
+    pub sum_sq: f64, // of differences from the mean
+}
+
+impl RunningMean {
+    pub fn push(&mut self, value: f64) {
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.sum_sq += delta * (value - self.mean);
+    }
+
+    // of a 95% confidence interval on the mean
+    pub fn half_width(&self) -> f64 {
+        if self.count < 2 {
+            return f64::INFINITY;
+        }
+        1.96 * (self.sum_sq / (self.count - 1) as f64 / self.count as f64).sqrt()
+    }
+}
+
+// converging on fewer successes than this is more likely luck than a stable mean
+pub const MIN_CONVERGED_TRIALS: usize = 100;
+
+// trials run in parallel between convergence checks
+pub const CONVERGE_BATCH: usize = 500;
+
+pub fn run_trials(mob: &RollsGemtable, context: &GameContext, config: &SimConfig, trials: usize, seed: u64) -> Vec<Option<TallyReport>> {
+    run_trials_timed(mob, context, config, trials, seed, None).0
+}
+
+// same trials, plus how many ticks each failed one had gone before it died or ran the bank dry. with a tolerance it
+// stops as soon as the mean hours of the successful trials is known to within that fraction,
+// so `trials` is only the cap
+pub fn run_trials_timed(mob: &RollsGemtable, context: &GameContext, config: &SimConfig, trials: usize, seed: u64,
+                    converge: Option<f64>) -> (Vec<Option<TallyReport>>, Vec<usize>) {
+    // every trial seeds its own rng off its index and starts from a reset player, so it comes out
+    // the same whichever thread runs it
+    let run_one = |player: &mut Player, profiles: &mut MatchupProfiles, trial: usize| {
+        player.reset_for_trial();
+        let mut deaths = Vec::new();
+        let mut on_event = |event| {
+            if let SimEvent::Died { elapsed, .. } | SimEvent::OutOfFood { elapsed, .. } = event {
+                deaths.push(elapsed);
+            }
+        };
+        let ticks_to_talisman = run_trial(mob, context, player, profiles, config, &mut trial_rng(seed, trial), &mut on_event);
+        #[cfg(feature = "logging")]
+        log_trial(mob, trial, &ticks_to_talisman);
+        if let Some(progress) = &config.progress {
+            progress.trial_done(&mob.name, trial, trials);
+        }
+        (ticks_to_talisman, deaths)
+    };
+    // converging is checked in trial order, so batches only run a little past where it stops
+    let batch = match (converge, config.sequential) {
+        (None, _) => trials.max(1),
+        (Some(_), true) => 1,
+        (Some(_), false) => CONVERGE_BATCH,
+    };
+
+    let mut hours = RunningMean::default();
+    let mut trial_ticks = Vec::new();
+    let mut death_ticks = Vec::new();
+    let mut player = context.player.clone();
+    let mut profiles = MatchupProfiles::new(&player, mob);
+    let mut start = 0;
+    while start < trials {
+        let end = (start + batch).min(trials);
+        let results: Vec<_> = if config.sequential {
+            (start..end).map(|trial| run_one(&mut player, &mut profiles, trial)).collect()
+        } else {
+            (start..end).into_par_iter().map_init(
+                || {
+                    let player = context.player.clone();
+                    let profiles = MatchupProfiles::new(&player, mob);
+                    (player, profiles)
+                },
+                |(player, profiles), trial| run_one(player, profiles, trial),
+            ).collect()
+        };
+        for (ticks_to_talisman, deaths) in results {
+            if let Some(report) = &ticks_to_talisman {
+                hours.push(report.to_ticks() as f64 / 6000.0);
+            }
+            trial_ticks.push(ticks_to_talisman);
+            death_ticks.extend(deaths);
+            let converged = converge.is_some_and(|tolerance| {
+                hours.count >= MIN_CONVERGED_TRIALS && hours.half_width() <= tolerance * hours.mean
+            });
+            if converged {
+                if let Some(progress) = &config.progress {
+                    progress.advance(trials - end, &mob.name, trial_ticks.len() - 1, trials);
+                }
+                return (trial_ticks, death_ticks);
+            }
+        }
+        start = end;
+    }
+    (trial_ticks, death_ticks)
+}
+
+// the lowest eat threshold that keeps deaths under max_death_rate, which is also the one that eats least.
+// scans up from 1hp since the low thresholds die quickly and are cheap to rule out
+pub fn sweep_eat_threshold(mob: &RollsGemtable, context: &GameContext, config: &SimConfig, trials: usize, seed: u64, max_death_rate: f64) -> Option<(u32, SimSummary)> {
+    // no trials is no evidence any threshold is safe
+    if trials == 0 {
+        return None;
+    }
+    (1..context.player.stats.hp_level).find_map(|threshold| {
+        let config = SimConfig { eat_threshold: Some(threshold), ..config.clone() };
+        let summary = summarize_search(mob, context, &config, &run_trials(mob, context, &config, trials, seed));
+        let death_rate = summary.death_rate();
+        sim_debug!("{} eating below {threshold}: {:.2}% deaths", mob.name, death_rate * 100.0);
+        (death_rate < max_death_rate).then_some((threshold, summary))
+    })
+}
+
+// a grind we'd actually recommend: rarely dies, finishes within max_hours and isn't spending more
+// time walking to the bank for food than it spends at the spot
+pub fn is_viable(summary: &SimSummary, mob: &RollsGemtable, max_death_rate: f64, max_hours: f64) -> bool {
+    let (Some(hours), Some(banks)) = (summary.avg_hours, summary.avg_banks) else {
+        return false;
+    };
+    let food_trip_hours = banks[0] * mob.ticks_between_trips as f64 / 6000.0;
+    summary.death_rate() < max_death_rate && hours <= max_hours && food_trip_hours <= hours / 2.0
+}
+
+// the lowest balanced combat level and then the cheapest food that makes the mob viable, keeping the
+// configured gear. assumes a higher level never makes things worse, so it binary searches the levels
+// with the best food, then tries the foods cheapest first at the level it found. that's at most
+// seven searches for the level and five for the food rather than the whole grid
+pub fn solve_min_setup(mob: &RollsGemtable, context: &GameContext, config: &SimConfig, trials: usize, seed: u64,
+                   max_death_rate: f64, max_hours: f64) -> Option<(CombatStats, FoodKind, SimSummary)> {
+    let try_setup = |cb: u32, food: &FoodKind| {
+        let player = context.player.clone().with_stats(CombatStats::for_combat_level(cb, StyleProfile::Balanced));
+        let context = GameContext::new(context.is_members, player);
+        let config = SimConfig { food: vec![(food.clone(), config.food_per_trip())], ..config.clone() };
+        let summary = summarize_search(mob, &context, &config, &run_trials(mob, &context, &config, trials, seed));
+        sim_debug!("{} at combat {cb} eating {}: {:.2}% deaths", mob.name, food.name, summary.death_rate() * 100.0);
+        is_viable(&summary, mob, max_death_rate, max_hours).then_some(summary)
+    };
+    let foods = FoodKind::tiers();
+    let best_food = &foods[foods.len() - 1];
+    let (mut low, mut high) = (3, 126);
+    try_setup(high, best_food)?;
+    while low < high {
+        let mid = (low + high) / 2;
+        if try_setup(mid, best_food).is_some() {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+    foods.iter().find_map(|food| {
+        let summary = try_setup(low, food)?;
+        Some((CombatStats::for_combat_level(low, StyleProfile::Balanced), food.clone(), summary))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::*;
+
+    // coins at face value, priced items at the table's price and anything unknown at nothing
+    #[test]
+    fn loot_value_at_the_table_prices() {
+        let config = SimConfig::default();
+        let loot: HashMap<String, u64> = [("coins", 1000), ("uncut_ruby", 2), ("mystery_box", 5)].into_iter()
+            .map(|(name, total)| (name.to_string(), total))
+            .collect();
+        assert_eq!(config.loot_value(&loot), 2600);
+        assert_eq!(config.unpriced(&loot), ["mystery_box"]);
+    }
+
+    // an aggressive mob is on us the tick it spawns, so the first damage of the fight is its swing even
+    // when ours lands the same tick and kills it. the script is its hit for 1, our hit for 1 on its 1 hp
+    // and a gem roll that can't land, a second swing from either side would run it dry
+    #[test]
+    fn aggressive_mob_swings_first() {
+        let mut mob = builtin("dwarf");
+        mob.aggressive = true;
+        mob.chance = 0;
+        mob.stats = CombatStats::full(mob.stats.att_level, mob.stats.str_level, mob.stats.def_level, 1);
+        let context = GameContext::new(true, default_player());
+        let config = SimConfig { goal: Goal::Kills(1), ..SimConfig::default() };
+        let script = [Draw::Float(0.0), Draw::Int(0), Draw::Float(0.0), Draw::Int(0), Draw::Int(0)];
+        let mut player = context.player.clone();
+        let mut profiles = MatchupProfiles::new(&player, &mob);
+        player.reset_for_trial();
+        let report = run_trial(&mob, &context, &mut player, &mut profiles, &config, &mut ScriptedRng::new(script), &mut |_| {});
+        assert_eq!(report.map(|report| report.kills), Some(1));
+        assert_eq!(player.stats.current_hp, player.stats.hp_level - 1);
+
+        // the same in a fight to the death, where the mob's opening hit is counted before our kill
+        let mut player = context.player.clone();
+        let script = [Draw::Float(0.0), Draw::Int(0), Draw::Float(0.0), Draw::Int(0)];
+        let outcome = fight_to_death(&mut player, &mut mob.clone(), &config, &mut ScriptedRng::new(script));
+        assert_eq!((outcome.mob_damage, outcome.player_damage, outcome.ticks), (1, 1, 1));
+    }
+
+    fn sparring_partner() -> Player {
+        melee_player(MeleeDps { accuracy: 40, ..bare_weapon() }, CombatStats::full(60, 60, 1, 5000))
+    }
+
+    // the mob's damage a tick across many fights against a player too big to drop, returned with how
+    // long each fight ran. regen is added back so the hp lost is all the mob's doing
+    fn mob_dps_and_fight_lengths(mob: &RollsGemtable, random_start_tick: bool) -> (f64, Vec<usize>) {
+        let context = GameContext::new(true, sparring_partner());
+        let config = SimConfig { goal: Goal::Kills(50), food: Vec::new(), eat_threshold: Some(0), random_start_tick, ..SimConfig::default() };
+        let (mut damage, mut ticks, mut lengths) = (0, 0, Vec::new());
+        for trial in 0..40 {
+            let mut player = context.player.clone();
+            let mut profiles = MatchupProfiles::new(&player, mob);
+            player.reset_for_trial();
+            let mut last_kill = 0;
+            let report = run_trial(mob, &context, &mut player, &mut profiles, &config, &mut trial_rng(1, trial), &mut |event| {
+                if let SimEvent::Kill { tick } = event {
+                    lengths.push(tick - last_kill);
+                    last_kill = tick;
+                }
+            }).expect("5000 hp outlasts 50 kills");
+            damage += (player.stats.hp_level - player.stats.current_hp) as usize + report.regen_marks;
+            ticks += report.ticks_in_combat;
+        }
+        (damage as f64 / ticks as f64, lengths)
+    }
+
+    // randomizing where each fight starts in the mob's cycle moves when it swings, not how hard. a mob
+    // swinging every tick has to swing at all with the start left alone
+    #[test]
+    fn random_start_keeps_the_dps() {
+        let mob = RollsGemtable::builder()
+            .name("sparring dummy")
+            .levels(1, 1, 1, 40)
+            .chance(0, 1)
+            .accuracy(40)
+            .strength(40)
+            .respawn_rate(1)
+            .available_npcs(10)
+            .build()
+            .expect("the dummy is a valid mob");
+        let (fixed_dps, fixed_lengths) = mob_dps_and_fight_lengths(&mob, false);
+        let (random_dps, random_lengths) = mob_dps_and_fight_lengths(&mob, true);
+        let expected = precompute_attack(&mob, &sparring_partner()).expected_hit() / mob.attack_rate as f64;
+        assert!(fixed_dps > 0.0 && (random_dps / fixed_dps - 1.0).abs() < 0.1, "{random_dps:.3} randomized against {fixed_dps:.3}");
+        assert!((random_dps / expected - 1.0).abs() < 0.1, "{random_dps:.3} randomized against {expected:.3} off the profile");
+        let spread = |lengths: &[usize]| lengths.iter().collect::<HashSet<_>>().len();
+        assert!(spread(&random_lengths) > 5 && spread(&fixed_lengths) > 1, "{random_lengths:?}");
+
+        let every_tick = RollsGemtable { attack_rate: 1, ..mob };
+        assert!(mob_dps_and_fight_lengths(&every_tick, false).0 > fixed_dps * 2.0);
+    }
+
+    // a weapon equipped between swings is what the next swing hits with, and a new trial puts the
+    // configured one back. the cached profiles are shared by the whole search, so both have to show up in the key
+    #[test]
+    fn equip_mid_search_changes_the_max_hit() {
+        let mob = RollsGemtable::builder()
+            .name("training dummy")
+            .levels(1, 1, 1, 10000)
+            .chance(0, 1)
+            .respawn_rate(1)
+            .build()
+            .expect("the dummy is a valid mob");
+        let mut player = default_player();
+        let mut profiles = MatchupProfiles::new(&player, &mob);
+        // the top roll for the max hit the player's gear should have, a stale profile lands short of it
+        let max_swing = |player: &mut Player, profiles: &mut MatchupProfiles| {
+            let expected = precompute_attack(player, &mob).max_hit;
+            let script = [Draw::Float(0.0), Draw::Int(expected - 1)];
+            let mut mob = mob.clone();
+            let damage = player_swing(0, 0, player, &mut mob, profiles, &SimConfig::default(), &mut ScriptedRng::new(script)).0.damage;
+            (damage, expected)
+        };
+        let (damage, base_max) = max_swing(&mut player, &mut profiles);
+        assert_eq!(damage, base_max);
+
+        player.equip(Loadout::Melee(MeleeDps { str_bonus: 100, ..bare_weapon() })).expect("a valid loadout");
+        let (damage, new_max) = max_swing(&mut player, &mut profiles);
+        assert!(new_max > base_max);
+        assert_eq!(damage, new_max);
+
+        player.reset_for_trial();
+        assert_eq!(player.loadout, default_player().loadout);
+        assert_eq!(max_swing(&mut player, &mut profiles).0, base_max);
+    }
+
+    // a talisman that stays on the floor for want of a slot hasn't been found, so a pack that's all food
+    // sees it drop and keeps going, while one with room picks it up and that's the drop
+    #[test]
+    fn talisman_left_behind_isnt_found() {
+        let mob = RollsGemtable::builder()
+            .name("gem dummy")
+            .levels(1, 1, 1, 1)
+            .chance(1, 1)
+            .respawn_rate(1)
+            .available_npcs(10)
+            .build()
+            .expect("the dummy is a valid mob");
+        let context = GameContext::new(true, default_player());
+        for (food, found) in [(INVENTORY_SIZE, false), (INVENTORY_SIZE - 8, true)] {
+            let config = SimConfig { goal: Goal::Session(6000), food: vec![(FoodKind::salmon(), food)], ..SimConfig::default() };
+            let mut dropped = 0;
+            let report = search_talisman(&mob, &context, &config, &mut trial_rng(0, 0), &mut |event| {
+                if matches!(&event, SimEvent::Drop { item, .. } if item.name == "nature_talisman") {
+                    dropped += 1;
+                }
+            }).expect("the dummy can't hit back");
+            assert!(dropped > 0, "no talisman in {} kills", report.kills);
+            assert_eq!(report.got_drop, found, "{food} food");
+            assert_eq!(report.loot.contains_key("nature_talisman"), found, "{food} food");
+        }
+    }
+
+    // a sweep with no trials to go on finds nothing rather than calling the first threshold safe
+    #[test]
+    fn sweep_without_trials() {
+        let context = GameContext::new(true, default_player());
+        assert!(sweep_eat_threshold(&builtin("dwarf"), &context, &SimConfig::default(), 0, 0, 0.01).is_none());
+    }
+
+    // with regen off nothing puts hp back, so a long session against a dummy that can't die loses
+    // exactly what the mob's profile says it deals a tick, give or take the rolls
+    #[test]
+    fn hp_loss_without_regen_is_the_incoming_dps() {
+        let mob = RollsGemtable::builder()
+            .name("training dummy")
+            .levels(1, 1, 1, 10000)
+            .chance(0, 1)
+            .accuracy(40)
+            .strength(40)
+            .respawn_rate(1)
+            .build()
+            .expect("the dummy is a valid mob");
+        let context = GameContext::new(true, sparring_partner());
+        let config = SimConfig { goal: Goal::Session(12000), food: Vec::new(), eat_threshold: Some(0), regen: false, ..SimConfig::default() };
+        let mut player = context.player.clone();
+        let mut profiles = MatchupProfiles::new(&player, &mob);
+        player.reset_for_trial();
+        let report = run_trial(&mob, &context, &mut player, &mut profiles, &config, &mut trial_rng(2, 0), &mut |_| {})
+            .expect("5000 hp outlasts the session");
+        let lost = (player.stats.hp_level - player.stats.current_hp) as f64 / report.ticks_in_combat as f64;
+        let expected = expected_dps(&mob, &player);
+        assert!((lost / expected - 1.0).abs() < 0.05, "lost {lost:.4} hp a tick, expected {expected:.4}");
+    }
+}
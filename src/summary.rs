@@ -0,0 +1,548 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use crate::combat::*;
+use crate::inventory::*;
+use crate::loot::*;
+use crate::mob::*;
+use crate::player::*;
+use crate::sim::*;
+
+#[derive(Debug)]
+pub struct SimSummary {
+    pub name: String,
+    pub goal: Goal,
+    pub trials: usize,
+    pub successes: usize,
+    pub avg_hours: Option<f64>, // None when no trial succeeded
+    pub hour_spread: Option<[f64; 4]>, // min, median, 90th percentile and max hours of the successful trials
+    pub hour_histogram: Option<Vec<usize>>, // successful trials per equal slice of min..=max hours, with --histogram
+    pub avg_food: Option<f64>,
+    pub avg_banks: Option<[f64; 4]>, // food, loot, rare and recharge trips per trial
+    pub player_dps: f64, // analytic, from the same formula the fights roll against
+    pub mob_dps: f64,
+    pub luck_hours: Option<[f64; 3]>, // 50/90/99% thresholds
+    pub luckiest: Option<(f64, usize)>, // (hours, kills) of the fastest successful trial
+    pub unluckiest: Option<(f64, usize)>, // and of the slowest
+    pub loot: HashMap<String, u64>, // summed over every successful trial
+    pub tiers: BTreeMap<LootTier, (usize, u64)>, // (drops, gp) per tier over the same trials
+    pub success_hours: f64, // total hours across successful trials, to normalize the loot
+    pub combat_hours: f64, // the part of those spent fighting, no banking, spawn waits or looting
+    pub loot_gp: u64, // value of all that loot at the configured prices
+    pub unpriced: Vec<String>, // loot that wasn't in the price table, left out of loot_gp
+    pub recharge_gp: u64, // spent keeping a degrading weapon charged, over the same trials
+    pub food_gp: u64, // and on the food eaten
+    pub drops: usize, // successful trials that actually got the drop
+    pub antipoison: Option<[f64; 2]>, // doses drunk and hp cured stacks would have done before the next bank, per trial
+    pub effective_hours: Option<f64>, // per drop, with the time the trials that died wasted counted against it
+    pub drop_chance: Option<f64>, // fixed sessions only, share of survived sessions that got the drop
+}
+
+impl SimSummary {
+    // a death throws the whole trial away, but the hours it took still came out of someone's day
+    pub fn amortize_deaths(&mut self, death_ticks: &[usize]) {
+        if self.drops == 0 {
+            return;
+        }
+        let wasted = death_ticks.iter().sum::<usize>() as f64 / 6000.0;
+        self.effective_hours = Some((self.success_hours + wasted) / self.drops as f64);
+    }
+
+    pub fn food_rate(&self) -> Option<f64> {
+        let (Some(hours), Some(food)) = (self.avg_hours, self.avg_food) else {
+            return None;
+        };
+        (hours > 0.0).then(|| food / hours)
+    }
+
+    pub fn loot_rate(&self) -> Option<f64> {
+        (self.success_hours > 0.0).then(|| self.loot_gp as f64 / self.success_hours)
+    }
+
+    pub fn death_rate(&self) -> f64 {
+        if self.trials == 0 {
+            return 0.0;
+        }
+        1.0 - self.successes as f64 / self.trials as f64
+    }
+
+    // gp of food per surviving trial
+    pub fn food_cost(&self) -> f64 {
+        if self.successes == 0 {
+            return 0.0;
+        }
+        self.food_gp as f64 / self.successes as f64
+    }
+
+    pub fn loot_per_hour(&self) -> Vec<(String, f64)> {
+        let mut rates: Vec<(String, f64)> = self.loot.iter()
+            .map(|(name, total)| (name.clone(), *total as f64 / self.success_hours))
+            .collect();
+        rates.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        rates
+    }
+
+    pub const CSV_HEADER: &'static str = "name,trials,successes,deaths,mean_hours,median_hours,mean_food,p90_hours,gp_per_hour";
+
+    pub fn warn_unpriced(&self) {
+        if !self.unpriced.is_empty() {
+            eprintln!("warning: {:?} looted {} with no price, counted as 0 gp", self.name, self.unpriced.join(", "));
+        }
+    }
+
+    // anything with nothing to average is left empty rather than written as 0
+    pub fn csv_row(&self) -> String {
+        let cell = |value: Option<f64>| value.map_or(String::new(), |value| format!("{value:.4}"));
+        let spread = |i: usize| self.hour_spread.map(|spread| spread[i]);
+        [
+            csv_field(&self.name),
+            self.trials.to_string(),
+            self.successes.to_string(),
+            (self.trials - self.successes).to_string(),
+            cell(self.avg_hours),
+            cell(spread(1)),
+            cell(self.avg_food),
+            cell(spread(2)),
+            cell(self.loot_rate()),
+        ].join(",")
+    }
+
+    pub fn print(&self) {
+        let (Some(avg_hours), Some(avg_food)) = (self.avg_hours, self.avg_food) else {
+            println!("{:?} never succeeded, all {} trials died", self.name, self.trials);
+            return;
+        };
+        match (&self.goal, self.drop_chance) {
+            (Goal::Session(budget), Some(chance)) => {
+                let hours = *budget as f64 / 6000.0;
+                println!("{:?} {:.1}% chance of the drop in a {hours:.1} hour session, {avg_food} food eaten", self.name, chance * 100.0);
+            },
+            (Goal::Kills(kills), _) => println!("{:?} {kills} kills in {avg_hours:.1} hours, {avg_food} food eaten", self.name),
+            _ => println!("{:?} dropped in {avg_hours:.1} hours, {avg_food} food eaten", self.name),
+        }
+        let deaths = self.trials - self.successes;
+        println!("    {deaths} of {} trials died, {:.2}% succeeded", self.trials, (1.0 - self.death_rate()) * 100.0);
+        if let Some([min, median, _, max]) = self.hour_spread {
+            println!("    hours: min {min:.1}, median {median:.1}, max {max:.1}");
+            if let Some(counts) = &self.hour_histogram {
+                let width = (max - min) / counts.len() as f64;
+                let tallest = counts.iter().copied().max().unwrap_or(0).max(1);
+                for (i, count) in counts.iter().enumerate() {
+                    let from = min + width * i as f64;
+                    println!("    {from:>7.1} - {:<7.1} {:<40} {count}", from + width, "#".repeat(count * 40 / tallest));
+                }
+            }
+        }
+        if let (Some(hours), true) = (self.effective_hours, self.successes < self.trials) {
+            println!("    counting the {:.2}% of trials that died: {hours:.1} hours per drop", self.death_rate() * 100.0);
+        }
+        println!("    dps: player {:.2}, mob {:.2}", self.player_dps, self.mob_dps);
+        if let Some(banks) = self.avg_banks.filter(|banks| banks[1..].iter().any(|trips| *trips > 0.0)) {
+            let reasons: Vec<String> = banks.iter().zip(["food", "loot", "rares", "recharging"])
+                .filter(|(trips, _)| **trips > 0.0)
+                .map(|(trips, reason)| format!("{trips:.1} for {reason}"))
+                .collect();
+            println!("    banks: {}", reasons.join(", "));
+        }
+        if let Some([doses, prevented]) = self.antipoison {
+            println!("    antipoison: {doses:.1} doses a trial, cured poison would have done {prevented:.0} hp before the next bank");
+        }
+        if let (Some(luck), false) = (self.luck_hours, matches!(self.goal, Goal::Kills(_))) {
+            println!("    luck: 50% by {:.1} hours, 90% by {:.1} hours, 99% by {:.1} hours", luck[0], luck[1], luck[2]);
+        }
+        if let (Goal::Talisman, Some((best_hours, best_kills)), Some((worst_hours, worst_kills))) = (&self.goal, self.luckiest, self.unluckiest) {
+            println!("    luckiest: {best_hours:.1} hours ({best_kills} kills), unluckiest: {worst_hours:.1} hours ({worst_kills} kills)");
+        }
+        if self.success_hours > 0.0 {
+            let top: Vec<String> = self.loot_per_hour().iter()
+                .take(5)
+                .map(|(name, rate)| format!("{name} {rate:.2}"))
+                .collect();
+            if !top.is_empty() {
+                println!("    loot/hr: {}", top.join(", "));
+            }
+            let loot_rate = self.loot_gp as f64 / self.success_hours;
+            let food_rate = self.food_gp as f64 / self.success_hours;
+            let recharge_rate = self.recharge_gp as f64 / self.success_hours;
+            let mut parts = vec![format!("{loot_rate:.0} loot"), format!("{food_rate:.0} food")];
+            if self.recharge_gp > 0 {
+                parts.push(format!("{recharge_rate:.0} recharging"));
+            }
+            let net = self.loot_gp as f64 - self.food_gp as f64 - self.recharge_gp as f64;
+            parts.push(format!("{:.0} net", net / self.success_hours));
+            println!("    gp/hr: {}", parts.join(", "));
+            self.warn_unpriced();
+            if self.combat_hours > 0.0 {
+                println!("    active gp/hr: {:.0} loot, {:.0} net counting only time in combat",
+                    self.loot_gp as f64 / self.combat_hours, net / self.combat_hours);
+            }
+        }
+        if self.success_hours > 0.0 && !self.tiers.is_empty() {
+            let per_hour: Vec<String> = self.tiers.iter()
+                .map(|(tier, (drops, gp))| format!("{} {:.2} ({:.0} gp)", tier.label(), *drops as f64 / self.success_hours, *gp as f64 / self.success_hours))
+                .collect();
+            println!("    tiers/hr: {}", per_hour.join(", "));
+            if let Some((drops, _)) = self.tiers.get(&LootTier::MegaRare) {
+                println!("    a mega-rare roughly every {:.1} hours", self.success_hours / *drops as f64);
+            }
+        }
+        if self.drops > 0 {
+            let per = if let Goal::Kills(_) = self.goal { "task" } else { "drop" };
+            println!("    food cost: {:.0} gp per {per}", self.food_gp as f64 / self.drops as f64);
+        }
+        if let Goal::Session(_) | Goal::Kills(_) = self.goal {
+            let mut per_session: Vec<(&String, f64)> = self.loot.iter()
+                .map(|(name, total)| (name, *total as f64 / self.successes as f64))
+                .collect();
+            per_session.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+            let top: Vec<String> = per_session.iter()
+                .take(5)
+                .map(|(name, count)| format!("{name} {count:.2}"))
+                .collect();
+            if !top.is_empty() {
+                let per = if let Goal::Kills(_) = self.goal { "task" } else { "session" };
+                println!("    loot/{per}: {}", top.join(", "));
+            }
+        }
+    }
+}
+
+// best candidate first. hours to the drop (or the session drop chance) to the tenth as printed, then
+// the lower death rate, then the cheaper food, then the name so the order never depends on input order
+pub fn compare_summaries(a: &SimSummary, b: &SimSummary) -> Ordering {
+    let tenths = |value: f64| (value * 10.0).round();
+    let primary = match (&a.goal, a.drop_chance, b.drop_chance) {
+        (Goal::Session(_), Some(a_chance), Some(b_chance)) => tenths(b_chance * 100.0).total_cmp(&tenths(a_chance * 100.0)),
+        _ => match (a.avg_hours, b.avg_hours) {
+            (Some(a_hours), Some(b_hours)) => tenths(a_hours).total_cmp(&tenths(b_hours)),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        },
+    };
+    primary
+        .then_with(|| a.death_rate().total_cmp(&b.death_rate()))
+        .then_with(|| a.food_cost().total_cmp(&b.food_cost()))
+        .then_with(|| a.name.cmp(&b.name))
+}
+
+// mean extra hours `other` takes over `base` trial by trial, with a 95% interval. with a shared seed
+// both sides start each trial from the same luck, which tightens this over differencing the averages.
+// only trials both sides survived count, None if there are fewer than two
+pub fn paired_difference(base: &[Option<TallyReport>], other: &[Option<TallyReport>]) -> Option<(f64, f64, usize)> {
+    let diffs: Vec<f64> = base.iter().zip(other)
+        .filter_map(|(a, b)| Some((b.as_ref()?.to_ticks() as f64 - a.as_ref()?.to_ticks() as f64) / 6000.0))
+        .collect();
+    if diffs.len() < 2 {
+        return None;
+    }
+    let n = diffs.len() as f64;
+    let mean = diffs.iter().sum::<f64>() / n;
+    let variance = diffs.iter().map(|diff| (diff - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    Some((mean, 1.96 * (variance / n).sqrt(), diffs.len()))
+}
+
+// None rather than NaN when there's nothing to average
+pub fn mean(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), val| (sum + val, count + 1));
+    if count == 0 {
+        None
+    } else {
+        Some(sum / count as f64)
+    }
+}
+
+// quoted when a spreadsheet could otherwise split or misread it, like the space in "ice giant"
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', ' ', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+pub const HISTOGRAM_BUCKETS: usize = 10;
+
+// how many of `sorted` land in each of `buckets` equal slices of its range, the max going in the last.
+// when everything's the same there's no range to slice, so it all comes back as a single bucket
+pub fn bucket_counts(sorted: &[f64], buckets: usize) -> Vec<usize> {
+    let (Some(min), Some(max)) = (sorted.first(), sorted.last()) else {
+        return vec![0; buckets];
+    };
+    if max <= min {
+        return vec![sorted.len()];
+    }
+    let width = (max - min) / buckets as f64;
+    let mut counts = vec![0; buckets];
+    for value in sorted {
+        counts[(((value - min) / width) as usize).min(buckets - 1)] += 1;
+    }
+    counts
+}
+
+// interpolating between the two nearest values, so the median of an even count is their midpoint.
+// `sorted` can't be empty
+pub fn percentile(sorted: &[f64], q: f64) -> f64 {
+    let rank = q.clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+    let (below, above) = (rank.floor() as usize, rank.ceil() as usize);
+    sorted[below] + (sorted[above] - sorted[below]) * (rank - below as f64)
+}
+
+pub fn summarize_search(mob: &RollsGemtable, context: &GameContext, config: &SimConfig, trial_ticks: &[Option<TallyReport>]) -> SimSummary {
+    let successes: Vec<usize> = trial_ticks.iter()
+        .filter_map(|t| t.as_ref().map(|t| t.to_ticks()))
+        .collect();
+    let avg_hours = mean(successes.iter().map(|ticks| *ticks as f64 / 6000.0));
+    let mut sorted_hours: Vec<f64> = successes.iter().map(|ticks| *ticks as f64 / 6000.0).collect();
+    sorted_hours.sort_by(f64::total_cmp);
+    let hour_spread = (!sorted_hours.is_empty()).then(|| [0.0, 0.5, 0.9, 1.0].map(|q| percentile(&sorted_hours, q)));
+    let hour_histogram = (config.histogram && !sorted_hours.is_empty()).then(|| bucket_counts(&sorted_hours, HISTOGRAM_BUCKETS));
+    let avg_food = mean(trial_ticks.iter()
+        .filter_map(|t| t.as_ref().map(|report| report.food_eaten as f64)));
+
+    let avg_banks = mean(trial_ticks.iter().flatten().map(|report| report.food_banks as f64)).map(|food| {
+        let avg = |trips: fn(&TallyReport) -> usize| mean(trial_ticks.iter().flatten().map(|report| trips(report) as f64)).unwrap_or(0.0);
+        [food, avg(|report| report.loot_banks), avg(|report| report.rare_banks), avg(|report| report.charge_banks)]
+    });
+
+    // a drop rule can depend on anything, so there are no closed form odds to go on
+    let p = if config.drop_rule.is_some() { 0.0 } else { talisman_chance(mob, context, config) };
+    let total_kills = trial_ticks.iter()
+        .filter_map(|t| t.as_ref().map(|report| report.kills))
+        .sum::<usize>();
+    let luck_hours = if p > 0.0 && total_kills > 0 {
+        // kill rate comes from the trials, the drop odds from the tables
+        let ticks_per_kill = successes.iter().sum::<usize>() as f64 / total_kills as f64;
+        Some([0.5, 0.9, 0.99].map(|confidence| {
+            kills_for_confidence(p, confidence) * ticks_per_kill / 6000.0
+        }))
+    } else {
+        None
+    };
+
+    let hours_and_kills = |report: &TallyReport| (report.to_ticks() as f64 / 6000.0, report.kills);
+    let luckiest = trial_ticks.iter().flatten()
+        .min_by_key(|report| report.to_ticks())
+        .map(hours_and_kills);
+    let unluckiest = trial_ticks.iter().flatten()
+        .max_by_key(|report| report.to_ticks())
+        .map(hours_and_kills);
+
+    let mut loot = HashMap::new();
+    for report in trial_ticks.iter().flatten() {
+        for (name, quantity) in &report.loot {
+            *loot.entry(name.clone()).or_insert(0) += *quantity as u64;
+        }
+    }
+
+    let mut tiers = BTreeMap::new();
+    for report in trial_ticks.iter().flatten() {
+        for (tier, (drops, gp)) in &report.tiers {
+            let (total_drops, total_gp) = tiers.entry(*tier).or_insert((0, 0));
+            *total_drops += drops;
+            *total_gp += gp;
+        }
+    }
+    let loot_gp = config.loot_value(&loot);
+    let unpriced = config.unpriced(&loot);
+    let gp_per_charge = context.player.charges.as_ref().map_or(0, |charges| charges.gp_per_charge);
+    let recharge_gp = trial_ticks.iter().flatten()
+        .map(|report| report.charges_recharged * gp_per_charge)
+        .sum();
+
+    let food_gp = trial_ticks.iter().flatten()
+        .flat_map(|report| report.food_eaten_by_kind.iter())
+        .map(|(name, eaten)| config.value_of(&Item::new(name, *eaten as usize)))
+        .sum();
+    let drops = trial_ticks.iter().flatten().filter(|report| report.got_drop).count();
+    let antipoison = config.antipoison.and_then(|_| {
+        let doses = mean(trial_ticks.iter().flatten().map(|report| report.antipoison_doses as f64))?;
+        let prevented = mean(trial_ticks.iter().flatten().map(|report| report.poison_prevented as f64))?;
+        Some([doses, prevented])
+    });
+
+    let drop_chance = match config.goal {
+        Goal::Session(_) => mean(trial_ticks.iter().flatten().map(|report| if report.got_drop { 1.0 } else { 0.0 })),
+        Goal::Talisman | Goal::Level(..) | Goal::Item(_) | Goal::Kills(_) => None,
+    };
+
+    SimSummary {
+        name: mob.name.clone(),
+        goal: config.goal.clone(),
+        trials: trial_ticks.len(),
+        successes: successes.len(),
+        avg_hours,
+        hour_spread,
+        hour_histogram,
+        avg_food,
+        avg_banks,
+        player_dps: player_dps_against(&context.player, mob),
+        mob_dps: mob_dps_against(mob, &context.player),
+        luck_hours,
+        luckiest,
+        unluckiest,
+        loot,
+        tiers,
+        success_hours: successes.iter().sum::<usize>() as f64 / 6000.0,
+        combat_hours: trial_ticks.iter().flatten().map(|report| report.ticks_in_combat).sum::<usize>() as f64 / 6000.0,
+        loot_gp,
+        unpriced,
+        recharge_gp,
+        food_gp,
+        drops,
+        antipoison,
+        effective_hours: None,
+        drop_chance,
+    }
+}
+
+#[cfg(feature = "logging")]
+pub fn log_trial(mob: &RollsGemtable, trial: usize, report: &Option<TallyReport>) {
+    match report {
+        Some(report) => log::info!("{} trial {trial}: {} kills, {} ticks, {} food", mob.name, report.kills, report.to_ticks(), report.food_eaten),
+        None => log::info!("{} trial {trial}: died", mob.name),
+    }
+}
+
+pub fn search_talismans(mob: &RollsGemtable, context: &GameContext, config: &SimConfig, trials: usize, seed: u64) -> SimSummary {
+    let (trial_ticks, death_ticks) = run_trials_timed(mob, context, config, trials, seed, config.converge);
+    let mut summary = summarize_search(mob, context, config, &trial_ticks);
+    summary.amortize_deaths(&death_ticks);
+    if config.format == OutputFormat::Csv {
+        summary.warn_unpriced();
+        println!("{}", summary.csv_row());
+        return summary;
+    }
+    summary.print();
+    if let Some(tolerance) = config.converge {
+        if summary.trials < trials {
+            println!("    converged to within {:.1}% after {} of {trials} trials", tolerance * 100.0, summary.trials);
+        } else {
+            println!("    still not within {:.1}% after all {trials} trials", tolerance * 100.0);
+        }
+    }
+    summary
+}
+
+// the same trials tanking the poison instead. banking cures poison, so without antipoison we bank
+// sooner and the hp the cured stacks would have done overstates the food it saves, this measures it
+pub fn antipoison_savings(mob: &RollsGemtable, context: &GameContext, config: &SimConfig, with: &SimSummary, trials: usize, seed: u64) {
+    let config = SimConfig { antipoison: None, ..config.clone() };
+    let without = summarize_search(mob, context, &config, &run_trials(mob, context, &config, trials, seed));
+    if let (Some(with_rate), Some(without_rate)) = (with.food_rate(), without.food_rate()) {
+        println!("    without antipoison: {without_rate:.1} food/hr instead of {with_rate:.1}, {:.2}% deaths instead of {:.2}%",
+            without.death_rate() * 100.0, with.death_rate() * 100.0);
+    }
+}
+
+// the same trials on a members world and a free one, the talisman and gem-table rares are members only
+pub fn compare_membership(mob: &RollsGemtable, context: &GameContext, config: &SimConfig, trials: usize, seed: u64) {
+    let f2p = GameContext::new(false, context.player.clone());
+    let members_trials = run_trials(mob, context, config, trials, seed);
+    let members = summarize_search(mob, context, config, &members_trials);
+    print!("members: ");
+    members.print();
+    if config.goal == Goal::Talisman && talisman_chance(mob, &f2p, config) == 0.0 {
+        println!("f2p: {:?} never drops the talisman", mob.name);
+        return;
+    }
+    let f2p_trials = run_trials(mob, &f2p, config, trials, seed);
+    let free = summarize_search(mob, &f2p, config, &f2p_trials);
+    print!("f2p: ");
+    free.print();
+    let fixed_length = matches!(config.goal, Goal::Session(_));
+    if let Some((diff, half_width, pairs)) = paired_difference(&members_trials, &f2p_trials).filter(|_| !fixed_length) {
+        println!("    f2p vs members: {diff:+.2} ± {half_width:.2} hours (95% CI over {pairs} paired trials)");
+    }
+    if let (Some(members_rate), Some(f2p_rate)) = (members.loot_rate(), free.loot_rate()) {
+        println!("    f2p vs members: {:+.0} loot gp/hr", f2p_rate - members_rate);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::*;
+
+    // a mob that one-shots a 10hp player, the summary has to count the deaths without anything to average
+    #[test]
+    fn lethal_mob_summarizes_without_nan() {
+        let mut mob = builtin("dwarf");
+        mob.strength = 500;
+        mob.accuracy = 500;
+        mob.stats.str_level = 99;
+        mob.stats.att_level = 99;
+        let mut player = default_player();
+        player.base_stats.hp_level = 10;
+        player.base_stats.current_hp = 10;
+        let context = GameContext::new(true, player);
+        let config = SimConfig::default();
+        let summary = summarize_search(&mob, &context, &config, &run_trials(&mob, &context, &config, 200, 0));
+        assert!(summary.trials > summary.successes, "{} of {} trials survived", summary.successes, summary.trials);
+        assert!(summary.avg_hours.is_none_or(f64::is_finite), "{:?}", summary.avg_hours);
+        assert!(summary.hour_spread.is_none_or(|spread| spread.iter().all(|hours| hours.is_finite())), "{:?}", summary.hour_spread);
+    }
+
+    // known trial lengths through summarize_search: 1, 1, 2, 3 and 10 hours over ten 0.9 hour buckets,
+    // the death left out and the 10 in the last bucket. three trials of 2 hours have no range to split
+    #[test]
+    fn hour_histogram_buckets() {
+        let mob = builtin("dwarf");
+        let context = GameContext::new(true, default_player());
+        let config = SimConfig { histogram: true, ..SimConfig::default() };
+        let reports = |hours: &[usize]| -> Vec<Option<TallyReport>> {
+            hours.iter().map(|hours| {
+                let mut report = TallyReport::new();
+                report.ticks_in_combat = hours * 6000;
+                Some(report)
+            }).collect()
+        };
+        let mut spread = reports(&[3, 1, 10, 2, 1]);
+        spread.push(None);
+        let counts = |trials: &[Option<TallyReport>]| summarize_search(&mob, &context, &config, trials).hour_histogram;
+        assert_eq!(counts(&spread), Some(vec![2, 1, 1, 0, 0, 0, 0, 0, 0, 1]));
+        assert_eq!(counts(&reports(&[2, 2, 2])), Some(vec![3]));
+    }
+
+    #[test]
+    fn csv_fields_quote_what_needs_it() {
+        for (name, expected) in [("dwarf", "dwarf"), ("ice giant", "\"ice giant\""), ("giant, ice", "\"giant, ice\""), ("\"big\" giant", "\"\"\"big\"\" giant\"")] {
+            assert_eq!(csv_field(name), expected);
+        }
+    }
+
+    // no trials at all, or only deaths, leaves every average empty instead of dividing by zero
+    #[test]
+    fn summary_of_nothing() {
+        let mob = builtin("dwarf");
+        let context = GameContext::new(true, default_player());
+        for trials in [Vec::new(), vec![None, None]] {
+            let summary = summarize_search(&mob, &context, &SimConfig::default(), &trials);
+            assert_eq!((summary.trials, summary.successes), (trials.len(), 0));
+            assert_eq!((summary.avg_hours, summary.avg_food, summary.food_rate()), (None, None, None));
+            assert!(summary.hour_spread.is_none() && summary.luck_hours.is_none() && summary.avg_banks.is_none());
+            assert_eq!(summary.death_rate(), if trials.is_empty() { 0.0 } else { 1.0 });
+            assert!(!summary.csv_row().contains("NaN"), "{}", summary.csv_row());
+        }
+    }
+
+    // two mobs as quick as each other to the tenth of an hour, the one that never killed us ranks
+    // first even though the name would have put the other ahead
+    #[test]
+    fn tied_hours_go_to_the_safer() {
+        let context = GameContext::new(true, default_player());
+        let config = SimConfig::default();
+        let summary = |name: &str, deaths: usize| {
+            let mob = RollsGemtable { name: name.into(), ..builtin("dwarf") };
+            let mut trials: Vec<Option<TallyReport>> = [12000, 12100, 11900, 12000].into_iter().map(|ticks| {
+                let mut report = TallyReport::new();
+                report.ticks_in_combat = ticks;
+                Some(report)
+            }).collect();
+            trials.extend((0..deaths).map(|_| None));
+            summarize_search(&mob, &context, &config, &trials)
+        };
+        let mut ranked = [summary("a risky", 1), summary("z safe", 0)];
+        ranked.sort_by(compare_summaries);
+        assert_eq!(ranked.map(|summary| summary.name), ["z safe", "a risky"]);
+    }
+}
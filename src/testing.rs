@@ -0,0 +1,32 @@
+// setup the unit tests share, none of it is built outside cfg(test)
+
+use crate::combat::*;
+use crate::inventory::*;
+use crate::mob::*;
+use crate::player::*;
+
+// aggressive slash every 4 ticks with no bonuses, tests that care about the weapon change what they need
+pub fn bare_weapon() -> MeleeDps {
+    MeleeDps {
+        str_bonus: 0,
+        style: MeleeStyle::Aggressive,
+        attack_type: AttackType::Slash,
+        accuracy: 0,
+        rate: 4,
+        def_bonus: 0,
+    }
+}
+
+// a player standing at the origin with nothing but the weapon and the levels given
+pub fn melee_player(weapon: MeleeDps, stats: CombatStats) -> Player {
+    Player::new(Loadout::Melee(weapon), Inventory::default(), PlayerCoords::new(0, 0, 0), stats).expect("a valid loadout")
+}
+
+// what the command line fights with when it isn't told otherwise
+pub fn default_player() -> Player {
+    melee_player(MeleeDps { str_bonus: 30, accuracy: 69, rate: 5, def_bonus: 103, ..bare_weapon() }, CombatStats::full(60, 60, 40, 60))
+}
+
+pub fn builtin(name: &str) -> RollsGemtable {
+    builtin_candidates().into_iter().find(|mob| mob.name == name).expect("builtin mob")
+}
@@ -0,0 +1,139 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use rand::Rng;
+use crate::combat::*;
+use crate::config::*;
+use crate::loot::*;
+use crate::mob::*;
+use crate::player::*;
+use crate::sim::*;
+
+// pass/fail bookkeeping for --validate
+pub struct Validation {
+    pub failures: usize,
+}
+
+impl Validation {
+    pub fn check(&mut self, name: &str, ok: bool, detail: String) {
+        if ok {
+            println!("PASS {name}");
+        } else {
+            println!("FAIL {name}: {detail}");
+            self.failures += 1;
+        }
+    }
+}
+
+// within a few standard errors of the expected frequency
+pub fn rate_matches(observed: usize, rolls: usize, p: f64) -> bool {
+    let freq = observed as f64 / rolls as f64;
+    let stderr = (p * (1.0 - p) / rolls as f64).sqrt();
+    (freq - p).abs() <= 4.0 * stderr + 1e-9
+}
+
+pub fn validate_mob_data(mob: &RollsGemtable, validation: &mut Validation) {
+    let name = &mob.name;
+    validation.check(&format!("{name} gem roll odds"), mob.outof > 0 && mob.chance <= mob.outof,
+        format!("chance {} out of {}", mob.chance, mob.outof));
+    validation.check(&format!("{name} timings"), mob.attack_rate >= 1 && mob.respawn_rate >= 1,
+        format!("attack_rate {} respawn_rate {} need to be at least 1", mob.attack_rate, mob.respawn_rate));
+    validation.check(&format!("{name} hp"), mob.stats.hp_level > 0 && mob.stats.current_hp <= mob.stats.hp_level,
+        format!("current hp {} max {}", mob.stats.current_hp, mob.stats.hp_level));
+    if let Some(table) = &mob.main_drops {
+        let bad_range = table.entries.iter()
+            .filter_map(|(_, spec)| spec.as_ref())
+            .find(|spec| spec.quantity.is_empty());
+        validation.check(&format!("{name} drop quantities"), bad_range.is_none(),
+            format!("{bad_range:?} has an empty quantity range"));
+    }
+}
+
+pub fn validate_mob_table(mob: &RollsGemtable, rolls: usize, validation: &mut Validation, rng: &mut impl Rng) {
+    let Some(table) = &mob.main_drops else {
+        return;
+    };
+    if table.total_weight == 0 {
+        validation.check(&format!("{} drop table", mob.name), false, "table has no weight".into());
+        return;
+    }
+    let mut expected: HashMap<String, f64> = HashMap::new();
+    for (weight, spec) in &table.entries {
+        if let Some(spec) = spec {
+            *expected.entry(spec.name.clone()).or_insert(0.0) += *weight as f64 / table.total_weight as f64;
+        }
+    }
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for _ in 0..rolls {
+        if let Some(item) = table.roll(rng) {
+            *seen.entry(item.name).or_insert(0) += 1;
+        }
+    }
+    let mut names: Vec<&String> = expected.keys().collect();
+    names.sort();
+    for name in names {
+        let observed = seen.get(name).copied().unwrap_or(0);
+        validation.check(&format!("{} drops {name}", mob.name), rate_matches(observed, rolls, expected[name]),
+            format!("{observed} in {rolls} rolls, expected rate {:.4}", expected[name]));
+    }
+}
+
+// the fighter the runs will use, a loadout that never swings or a player that starts dead can't finish a trial
+pub fn validate_player(context: &GameContext, validation: &mut Validation) {
+    let player = &context.player;
+    validation.check("player attack rate", player.attack_rate() >= 1,
+        format!("attack_rate {} needs to be at least 1", player.attack_rate()));
+    validation.check("player hp", player.stats.hp_level > 0 && player.stats.current_hp <= player.stats.hp_level,
+        format!("current hp {} max {}", player.stats.current_hp, player.stats.hp_level));
+}
+
+// how often each item shows up in a real log of kills against how often the tables say it should,
+// true if nothing is off by more than chance would explain
+pub fn validate_against_log(path: &Path, mob: &RollsGemtable, context: &GameContext) -> Result<bool, ConfigError> {
+    let log: Vec<LoggedDrop> = read_drop_log(path)?.into_iter().filter(|drop| drop.mob == mob.name).collect();
+    let kills = log.iter().map(|drop| drop.kill).collect::<HashSet<_>>().len();
+    if kills == 0 {
+        return Ok(true);
+    }
+    let mut observed: HashMap<String, usize> = HashMap::new();
+    for name in log.iter().filter_map(|drop| drop.item.as_ref()) {
+        *observed.entry(name.clone()).or_insert(0) += 1;
+    }
+
+    // seeded so the same log always gets the same verdict
+    let mut rng = trial_rng(0, 0);
+    let rolls = 1_000_000;
+    let mut modelled: HashMap<String, usize> = HashMap::new();
+    for _ in 0..rolls {
+        for item in roll_kill_drops(mob, context, 1.0, &mut rng) {
+            *modelled.entry(item.name).or_insert(0) += 1;
+        }
+    }
+
+    let mut validation = Validation { failures: 0 };
+    let mut names: Vec<&String> = observed.keys().chain(modelled.keys()).collect::<HashSet<_>>().into_iter().collect();
+    names.sort();
+    for name in names {
+        let seen = observed.get(name).copied().unwrap_or(0);
+        let p = modelled.get(name).copied().unwrap_or(0) as f64 / rolls as f64;
+        validation.check(&format!("{} logged {name}", mob.name), rate_matches(seen, kills, p),
+            format!("{seen} in {kills} kills, model says {:.4} a kill", p));
+    }
+    Ok(validation.failures == 0)
+}
+
+// sanity checks a dataset before it gets a long run, true if everything passed
+pub fn run_validation(candidates: &[RollsGemtable], context: &GameContext, rng: &mut impl Rng) -> bool {
+    let mut validation = Validation { failures: 0 };
+    let rolls = 1_000_000;
+    for mob in candidates {
+        validate_mob_data(mob, &mut validation);
+        validate_mob_table(mob, rolls, &mut validation, rng);
+    }
+    validate_player(context, &mut validation);
+    if validation.failures == 0 {
+        println!("all checks passed");
+    } else {
+        println!("{} checks failed", validation.failures);
+    }
+    validation.failures == 0
+}
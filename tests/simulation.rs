@@ -0,0 +1,430 @@
+// the library from the outside, the same way another binary would drive it
+
+use rs2sim::combat::*;
+use rs2sim::inventory::*;
+use rs2sim::mob::*;
+use rs2sim::player::*;
+use rs2sim::sim::*;
+use rs2sim::summary::*;
+
+fn dwarf() -> RollsGemtable {
+    builtin_candidates().into_iter().find(|mob| mob.name == "dwarf").expect("dwarf is a builtin")
+}
+
+fn player() -> Player {
+    Player::new(
+        Loadout::Melee(MeleeDps {
+            str_bonus: 30,
+            style: MeleeStyle::Aggressive,
+            attack_type: AttackType::Slash,
+            accuracy: 69,
+            def_bonus: 103,
+            rate: 5,
+        }),
+        Inventory::default(),
+        PlayerCoords::new(0, 0, 0),
+        CombatStats::full(60, 60, 40, 60),
+    ).expect("a valid loadout")
+}
+
+// aggressive slash every 4 ticks with no bonuses, tests that care about the weapon change what they need
+fn bare_weapon() -> MeleeDps {
+    MeleeDps {
+        str_bonus: 0,
+        style: MeleeStyle::Aggressive,
+        attack_type: AttackType::Slash,
+        accuracy: 0,
+        rate: 4,
+        def_bonus: 0,
+    }
+}
+
+fn melee_player(weapon: MeleeDps, stats: CombatStats) -> Player {
+    Player::new(Loadout::Melee(weapon), Inventory::default(), PlayerCoords::new(0, 0, 0), stats).expect("a valid loadout")
+}
+
+// something to hit that never dies, drops nothing and is back the tick it's gone
+fn training_dummy() -> RollsGemtableBuilder {
+    RollsGemtable::builder()
+        .name("training dummy")
+        .levels(1, 1, 1, 10000)
+        .chance(0, 1)
+        .respawn_rate(1)
+}
+
+#[test]
+fn seeded_search_reaches_the_goal_the_same_way_twice() {
+    let mob = dwarf();
+    let context = GameContext::new(true, player());
+    let config = SimConfig { goal: Goal::Kills(30), ..SimConfig::default() };
+    let mut kills = Vec::new();
+    let first = search_talisman(&mob, &context, &config, &mut trial_rng(42, 0), &mut |event| {
+        if let SimEvent::Kill { tick } = event {
+            kills.push(tick);
+        }
+    });
+    let second = search_talisman(&mob, &context, &config, &mut trial_rng(42, 0), &mut |_| {});
+
+    let report = first.expect("a 60 attack player doesn't die to dwarves");
+    assert_eq!(report.kills, 30);
+    assert_eq!(kills.len(), 30);
+    assert_eq!(Some(report), second);
+}
+
+#[test]
+fn profile_matches_the_wiki_max_hit() {
+    let mut target = dwarf();
+    target.defence = DefenceProfile::flat(0);
+    let player = Player::new(
+        Loadout::Melee(MeleeDps {
+            str_bonus: 82,
+            style: MeleeStyle::Aggressive,
+            attack_type: AttackType::Slash,
+            accuracy: 0,
+            def_bonus: 0,
+            rate: 4,
+        }),
+        Inventory::default(),
+        PlayerCoords::new(0, 0, 0),
+        CombatStats::full(1, 99, 1, 10),
+    ).expect("a valid loadout");
+    let profile = precompute_attack(&player, &target);
+    assert_eq!(profile.max_hit, 25);
+    assert_eq!(profile.rate, 4);
+    assert!(profile.hit_chance > 0.0 && profile.hit_chance < 1.0);
+}
+
+// every tick the trial spends at the spot is either a fight tick or a wait for a spawn. one dwarf at
+// a time so there's waiting to do, and one that rolls the gem table every kill so there's picking up,
+// with a few slots short of a pack of food to do it in. that time mustn't go through the loop
+#[test]
+fn ticks_at_the_spot_are_fighting_or_waiting() {
+    let mob = RollsGemtable { available_npcs: 1, chance: 1, outof: 1, ..dwarf() };
+    let context = GameContext::new(true, player());
+    let config = SimConfig {
+        goal: Goal::Session(30000),
+        food: vec![(FoodKind::salmon(), INVENTORY_SIZE - 8)],
+        loot_pickup_ticks: 2,
+        min_loot_value: 0,
+        ..SimConfig::default()
+    };
+    let mut ticks = 0;
+    let report = search_talisman(&mob, &context, &config, &mut trial_rng(7, 0), &mut |event| {
+        if let SimEvent::Tick { .. } = event {
+            ticks += 1;
+        }
+    }).expect("a 60 attack player doesn't die to dwarves");
+    assert_eq!(ticks, report.ticks_in_combat + report.ticks_waiting_for_spawn);
+    assert!(report.ticks_in_combat > 0 && report.ticks_waiting_for_spawn > 0 && report.ticks_looting > 0, "{report:?}");
+}
+
+// five salmon a trip against a dummy that can't die, with two trips' worth banked the third food trip
+// finds the bank empty and ends the trial. the salmon fills the pack in the second setup so the sharks
+// never get a slot, and their empty share mustn't look like the bank running dry
+#[test]
+fn banked_food_runs_out() {
+    let mob = training_dummy().accuracy(40).strength(20).build().expect("the dummy is a valid mob");
+    let context = GameContext::new(true, melee_player(bare_weapon(), CombatStats::full(1, 1, 1, 60)));
+    let setups = [
+        vec![(FoodKind::salmon(), 5)],
+        vec![(FoodKind::salmon(), INVENTORY_SIZE), (FoodKind::new("shark", 20), 5)],
+    ];
+    for food in setups {
+        let config = SimConfig { goal: Goal::Session(60000), food, ..SimConfig::default() };
+        let unlimited = search_talisman(&mob, &context, &config, &mut trial_rng(0, 0), &mut |_| {});
+        assert!(unlimited.is_some_and(|report| report.food_banks > 2));
+        let config = SimConfig { bank_food: Some(2), ..config };
+        let (mut banked, mut ran_out) = (0, false);
+        let limited = search_talisman(&mob, &context, &config, &mut trial_rng(0, 0), &mut |event| match event {
+            SimEvent::Banked { .. } => banked += 1,
+            SimEvent::OutOfFood { .. } => ran_out = true,
+            _ => {},
+        });
+        assert!(limited.is_none() && ran_out, "{:?}", config.food);
+        assert_eq!(banked, 2, "{:?}", config.food);
+    }
+}
+
+// a fixed hour against npcs that hit back hard, each meal should cost a few swings once
+// eating takes ticks, and the kills with them
+#[test]
+fn eating_delays_the_next_swing() {
+    let mob = RollsGemtable::builder()
+        .name("hungry dummy")
+        .levels(60, 60, 1, 30)
+        .chance(0, 1)
+        .attack_rate(2)
+        .accuracy(60)
+        .strength(60)
+        .respawn_rate(1)
+        .available_npcs(100)
+        .build()
+        .expect("the dummy is a valid mob");
+    let player = melee_player(MeleeDps { style: MeleeStyle::Accurate, accuracy: 100, ..bare_weapon() }, CombatStats::full(99, 99, 1, 99));
+    let context = GameContext::new(true, player);
+    let fight = |eat_delay: usize| {
+        let config = SimConfig { goal: Goal::Session(6000), food: vec![(FoodKind::salmon(), 28)], eat_delay, ..SimConfig::default() };
+        let mut meals = 0;
+        let report = search_talisman(&mob, &context, &config, &mut trial_rng(0, 0), &mut |event| {
+            if let SimEvent::Ate { .. } = event {
+                meals += 1;
+            }
+        });
+        (report.map_or(0, |report| report.kills), meals)
+    };
+    let (free_kills, free_meals) = fight(0);
+    let (slow_kills, slow_meals) = fight(3);
+    assert!(free_meals > 100 && slow_meals > 100, "{free_meals} and {slow_meals} meals");
+    assert!(slow_kills < free_kills, "{slow_kills} kills at 3 ticks a meal against {free_kills} eating for free");
+}
+
+// a 60s player should see off a dwarf and a fresh level 3 should drop to a hill giant, with the
+// loser's damage taken adding up to its hp either way
+#[test]
+fn fight_to_death_either_way() {
+    let fights = [
+        ("dwarf", CombatStats::full(60, 60, 60, 60), false),
+        ("hill giant", CombatStats::full(1, 1, 1, 10), true),
+    ];
+    for (name, stats, should_die) in fights {
+        let mut mob = builtin_candidates().into_iter().find(|mob| mob.name == name).expect("builtin mob");
+        let mut player = melee_player(MeleeDps { str_bonus: 30, accuracy: 69, rate: 5, def_bonus: 103, ..bare_weapon() }, stats);
+        let outcome = fight_to_death(&mut player, &mut mob, &SimConfig::default(), &mut trial_rng(0, 0));
+        assert_eq!(outcome.player_died, should_die, "{name}: {outcome:?}");
+        assert_eq!((player.is_dead(), mob.is_dead()), (should_die, !should_die), "{name}");
+        assert!(outcome.ticks > 0);
+        if should_die {
+            assert_eq!(outcome.mob_damage, player.stats.hp_level, "{name}");
+        } else {
+            assert_eq!(outcome.player_damage, mob.stats.hp_level, "{name}");
+        }
+    }
+}
+
+// a mob that always reaches the rare drop table and never the gem table, so everything picked up
+// came off the rare table, and the same mob without access drops nothing
+#[test]
+fn rare_drop_table_access() {
+    let without = RollsGemtable::builder()
+        .name("rare dummy")
+        .levels(1, 1, 1, 1)
+        .chance(0, 1)
+        .respawn_rate(1)
+        .build()
+        .expect("the dummy is a valid mob");
+    let with = RollsGemtable { rare_table: Some((1, 1)), ..without.clone() };
+    let player = melee_player(MeleeDps { style: MeleeStyle::Accurate, accuracy: 100, ..bare_weapon() }, CombatStats::full(60, 60, 60, 60));
+    let context = GameContext::new(true, player);
+    // nothing hits back, so no food and the whole pack is free for loot
+    let config = SimConfig { goal: Goal::Kills(200), food: Vec::new(), ..SimConfig::default() };
+    let rare_only = ["coins", "half_key1", "half_key2", "runite_bar", "naturerune", "lawrune", "deathrune", "rune_arrow",
+        "steel_arrow", "adamant_javelin", "rune_2h_sword", "rune_battleaxe", "rune_sq_shield", "rune_kiteshield",
+        "dragon_med_helm", "dragonstone", "cert_silver_ore"];
+    let report = |mob: &RollsGemtable| search_talisman(mob, &context, &config, &mut trial_rng(0, 0), &mut |_| {})
+        .expect("the dummy can't hit back");
+    let drops = |report: &TallyReport| report.tiers.values().map(|(drops, _)| drops).sum::<usize>();
+    let with_access = report(&with);
+    let looted: usize = rare_only.iter().filter_map(|name| with_access.loot.get(*name)).sum();
+    assert!(looted > 0 && drops(&with_access) > 100, "{looted} rare table items looted of {} drops", drops(&with_access));
+    assert_eq!(drops(&report(&without)), 0);
+}
+
+// a single trip against a mob that can't die, so the food eaten follows straight from the damage taken:
+// start at full hp, get topped up by regen, and eat a food each time we dip under the threshold
+#[test]
+fn first_trip_food() {
+    let mob = training_dummy().accuracy(40).strength(20).ticks_between_trips(1000).build().expect("the dummy is a valid mob");
+    let player = melee_player(bare_weapon(), CombatStats::full(1, 1, 1, 60));
+    let session = 600;
+    let config = SimConfig { goal: Goal::Session(session), ..SimConfig::default() };
+    let heal = config.food[0].0.heal as f64;
+    let slack = (player.stats.hp_level - config.eat_below(player.stats.hp_level)) as f64;
+    let swings = (0..session).filter(|tick| tick % mob.attack_rate == 1).count();
+    let damage = swings as f64 * precompute_attack(&mob, &player).expected_hit();
+    let regen = ((session - 1) / 100) as f64;
+    // we finish somewhere between the threshold and a food above it
+    let expected = (damage - regen - slack + heal / 2.0) / heal;
+
+    let context = GameContext::new(true, player);
+    let trials = run_trials(&mob, &context, &config, 1000, 0);
+    assert!(!trials.iter().flatten().any(|report| report.food_banks > 0), "ran out of food on the first trip");
+    let eaten = mean(trials.iter().flatten().map(|report| report.food_eaten as f64)).unwrap_or(0.0);
+    assert!((eaten - expected).abs() < 1.0, "ate {eaten:.2} a trial, expected {expected:.2}");
+}
+
+// the parallel trials have to come out as if they'd run one after another, stopping early included
+#[test]
+fn parallel_trials_match_sequential() {
+    let mob = dwarf();
+    let context = GameContext::new(true, player());
+    for converge in [None, Some(0.2)] {
+        let config = SimConfig { goal: Goal::Kills(20), converge, ..SimConfig::default() };
+        let parallel = run_trials_timed(&mob, &context, &config, 2000, 11, converge);
+        let config = SimConfig { sequential: true, ..config };
+        let sequential = run_trials_timed(&mob, &context, &config, 2000, 11, converge);
+        assert!(parallel == sequential, "converge {converge:?}: {} parallel trials against {} sequential", parallel.0.len(), sequential.0.len());
+    }
+}
+
+// the first trial where the gem table pays out has to have the gem in its loot. a full inventory of
+// food would leave it on the floor, so this brings a few slots short
+#[test]
+fn gems_dropped_are_looted() {
+    let mob = dwarf();
+    let context = GameContext::new(true, player());
+    let config = SimConfig {
+        goal: Goal::Session(60000),
+        food: vec![(FoodKind::salmon(), INVENTORY_SIZE - 8)],
+        ..SimConfig::default()
+    };
+    for trial in 0..100 {
+        let mut gems = Vec::new();
+        let report = search_talisman(&mob, &context, &config, &mut trial_rng(3, trial), &mut |event| {
+            if let SimEvent::Drop { item, .. } = event {
+                if item.tier == LootTier::Gem {
+                    gems.push(item.name);
+                }
+            }
+        });
+        let Some(report) = report.filter(|_| !gems.is_empty()) else {
+            continue;
+        };
+        let missing: Vec<&String> = gems.iter().filter(|gem| !report.loot.contains_key(*gem)).collect();
+        assert!(missing.is_empty(), "trial {trial} dropped {gems:?}, looted {:?}", report.loot);
+        return;
+    }
+    panic!("no gems in 100 trials");
+}
+
+// the same mob with and without aggression, over kills that all fit inside the 10 minute timeout.
+// striking first costs us food, and once it's timed out the aggressive one opens no more fights
+#[test]
+fn aggression_costs_food_and_times_out() {
+    let passive = RollsGemtable { aggressive: false, available_npcs: 10, respawn_rate: 10, ..dwarf() };
+    let aggressive = RollsGemtable { aggressive: true, ..passive.clone() };
+    let context = GameContext::new(true, player());
+    // topping up after every scratch, so each hit the mob gets in shows up as food
+    let eat_threshold = Some(context.player.stats.hp_level);
+    let config = SimConfig { goal: Goal::Kills(20), eat_threshold, ..SimConfig::default() };
+    let food = |mob: &RollsGemtable| {
+        let reports: Vec<TallyReport> = run_trials(mob, &context, &config, 500, 5).into_iter().flatten().collect();
+        let eaten: u32 = reports.iter().map(|report| report.food_eaten).sum();
+        let aggroed: usize = reports.iter().map(|report| report.times_aggroed).sum();
+        (eaten as f64 / reports.len().max(1) as f64, aggroed, reports.len())
+    };
+    let (passive_food, passive_aggro, _) = food(&passive);
+    let (aggressive_food, aggressive_aggro, aggressive_done) = food(&aggressive);
+    assert!(aggressive_food > passive_food, "{aggressive_food:.2} food a trial aggressive, {passive_food:.2} passive");
+    assert_eq!(passive_aggro, 0);
+    assert!(aggressive_aggro >= aggressive_done * 20, "{aggressive_aggro} aggroed over {aggressive_done} trials");
+
+    // timed out from the start, only the one already up when we arrive comes at us
+    let config = SimConfig { aggression_timeout: Some(0), ..config };
+    let tolerated = search_talisman(&aggressive, &context, &config, &mut trial_rng(5, 0), &mut |_| {});
+    assert_eq!(tolerated.map(|report| report.times_aggroed), Some(1));
+}
+
+// with more than one up, a kill moves us on to the next instead of hitting the corpse every tick
+#[test]
+fn no_corpse_kills() {
+    let context = GameContext::new(true, player());
+    let config = SimConfig { goal: Goal::Kills(50), ..SimConfig::default() };
+    let mut kills = Vec::new();
+    search_talisman(&dwarf(), &context, &config, &mut trial_rng(9, 0), &mut |event| {
+        if let SimEvent::Kill { tick } = event {
+            kills.push(tick);
+        }
+    });
+    let rate = context.player.attack_rate();
+    assert_eq!(kills.len(), 50);
+    assert!(kills.windows(2).all(|pair| pair[1] - pair[0] >= rate), "kills under {rate} ticks apart in {kills:?}");
+}
+
+// fire strike off a plain staff: the spell's max hit is the max hit, and a dwarf goes down to it
+#[test]
+fn fire_strike_session() {
+    let mob = dwarf();
+    let player = Player::new(
+        Loadout::Magic(MagicDps {
+            max_hit: 8,
+            accuracy: 10,
+            rate: 5,
+            def_bonus: 0,
+        }),
+        Inventory::default(),
+        PlayerCoords::new(0, 0, 0),
+        CombatStats::full(40, 1, 40, 40),
+    ).expect("a valid loadout");
+    let profile = precompute_attack(&player, &mob);
+    assert_eq!((profile.max_hit, profile.rate), (8, 5));
+    let config = SimConfig { goal: Goal::Session(5000), ..SimConfig::default() };
+    let trials = run_trials(&mob, &GameContext::new(true, player), &config, 20, 0);
+    assert!(trials.iter().flatten().map(|report| report.kills).sum::<usize>() > 0);
+}
+
+// a bow in every style through the same search the melee loadouts use, it should kill things and not panic
+#[test]
+fn ranged_sessions() {
+    let mob = dwarf();
+    for style in [RangedStyle::Accurate, RangedStyle::Rapid, RangedStyle::Longrange] {
+        let player = Player::new(
+            Loadout::Ranged(RangedDps {
+                ammo_str: 49,
+                accuracy: 69,
+                style: style.clone(),
+                rate: 5,
+                def_bonus: 0,
+            }),
+            Inventory::default(),
+            PlayerCoords::new(0, 0, 0),
+            CombatStats::full(40, 40, 40, 40),
+        ).expect("a valid loadout");
+        let config = SimConfig { goal: Goal::Session(5000), ..SimConfig::default() };
+        let trials = run_trials(&mob, &GameContext::new(true, player), &config, 20, 0);
+        assert!(trials.iter().flatten().map(|report| report.kills).sum::<usize>() > 0, "{style:?}");
+    }
+}
+
+// the same hour against a dummy that can't die eating sharks instead of salmon, the damage
+// taken is the same so fewer sharks go
+#[test]
+fn bigger_food_goes_further() {
+    let mob = training_dummy().accuracy(40).strength(20).build().expect("the dummy is a valid mob");
+    let context = GameContext::new(true, melee_player(bare_weapon(), CombatStats::full(1, 1, 1, 60)));
+    let eaten = |heal: u32| {
+        let config = SimConfig {
+            goal: Goal::Session(6000),
+            food: vec![(FoodKind::new(&format!("heals_{heal}"), heal), INVENTORY_SIZE)],
+            ..SimConfig::default()
+        };
+        let trials = run_trials(&mob, &context, &config, 200, 0);
+        mean(trials.iter().flatten().map(|report| report.food_eaten as f64)).unwrap_or(0.0)
+    };
+    let (small, big) = (eaten(9), eaten(20));
+    assert!(big > 0.0 && big < small, "{big:.1} eaten healing 20 against {small:.1} healing 9");
+}
+
+// a weapon that runs dry every 34 swings sends us on 150 tick bank trips all session, the first off
+// the swing at tick 99 so the walk starts right on a minute mark. the regen has to come out at one hp
+// per mark on the clock, whichever of the fight or the walk it fell in, the one a session ends on too
+#[test]
+fn regen_across_bank_trips() {
+    let mob = training_dummy().ticks_between_trips(150).build().expect("the dummy is a valid mob");
+    // enough hp that a long session's regen never reaches the cap
+    let mut stats = CombatStats::full(1, 1, 1, 5000);
+    stats.current_hp = 1;
+    let mut player = melee_player(MeleeDps { rate: 3, ..bare_weapon() }, CombatStats::full(1, 1, 1, 99))
+        .with_stats(stats)
+        .with_charges(WeaponCharges::new(34, 1, 0, None))
+        .expect("nothing to fall back on")
+        // whatever the dummy lands comes to nothing, and the points outlast every stretch between banks
+        .with_prayers(Prayers::new(99, 0, vec![Prayer::ProtectFromMelee]));
+    let config = SimConfig { goal: Goal::Session(60000), eat_threshold: Some(0), ..SimConfig::default() };
+    let context = GameContext::new(true, player.clone());
+    let mut profiles = MatchupProfiles::new(&player, &mob);
+    player.reset_for_trial();
+    let report = run_trial(&mob, &context, &mut player, &mut profiles, &config, &mut trial_rng(0, 0), &mut |_| {})
+        .expect("the dummy can't get through protect from melee");
+    assert!(report.charge_banks > 0);
+    assert_eq!(player.stats.current_hp as usize - 1, report.to_ticks() / 100);
+}